@@ -0,0 +1,132 @@
+// Reviewer highlights and notes; highlighted text is recovered from the characters whose
+// layout rectangles fall inside the marked regions
+
+use serde_json::{json, Value};
+
+use crate::poppler_ext;
+
+#[derive(Debug,Clone)]
+pub struct Annotation {
+    pub page: usize,
+    pub kind: &'static str,
+    pub text: Option<String>,
+    pub comment: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+}
+
+// "D:20190412103000+02'00'" -> "2019-04-12 10:30:00"
+pub fn pdf_date(s: &str) -> String {
+    let d = s.trim_start_matches("D:");
+    let digits = d.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+    if digits.len() < 8 {
+        return String::from(s)
+    }
+    let mut r = format!("{}-{}-{}", &digits[0..4], &digits[4..6], &digits[6..8]);
+    if digits.len() >= 12 {
+        r += &format!(" {}:{}", &digits[8..10], &digits[10..12]);
+        if digits.len() >= 14 {
+            r += &format!(":{}", &digits[12..14]);
+        }
+    }
+    r
+}
+
+fn inside(r: &poppler::ffi::PopplerRectangle, q: &poppler_ext::Rect) -> bool {
+    let cx = (r.x1 + r.x2) / 2.0;
+    let cy = (r.y1 + r.y2) / 2.0;
+    cx >= q.x1 && cx <= q.x2 && cy >= q.y1 && cy <= q.y2
+}
+
+// characters under the quads in reading order, line breaks and gaps become spaces
+fn marked_text(text: &str, layout: &[poppler::ffi::PopplerRectangle], quads: &[poppler_ext::Rect]) -> String {
+    let mut r = String::new();
+    let mut last = None;
+    for (i, (c, rect)) in text.chars().zip(layout.iter()).enumerate() {
+        if c == '\n' || !quads.iter().any(|q| inside(rect, q)) {
+            continue
+        }
+        if let Some(j) = last {
+            if i != j + 1 && !r.ends_with(' ') {
+                if r.ends_with('-') {
+                    r.pop();
+                }
+                else {
+                    r.push(' ');
+                }
+            }
+        }
+        r.push(c);
+        last = Some(i);
+    }
+    r.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn page_annotations(doc: &poppler::PopplerDocument, ext: &poppler_ext::Document, num: usize) ->
+  Result<Vec<Annotation>,glib::error::Error> {
+    let annots = ext.get_annots(num);
+    if annots.is_empty() {
+        return Ok(vec![])
+    }
+    let page = doc.get_page(num)?;
+    let text = page.get_text();
+    let layout = if text.is_empty() { vec![] } else { page.get_text_layout()? };
+    Ok(annots.into_iter().map(|a| {
+        let marked = if a.quads.is_empty() { None } else { Some(marked_text(&text, &layout, &a.quads)) };
+        Annotation {
+            page: num,
+            kind: a.kind,
+            text: marked.filter(|x| !x.is_empty()),
+            comment: a.contents,
+            author: a.author,
+            date: a.modified.as_ref().map(|x| pdf_date(x)),
+        }
+    }).collect())
+}
+
+pub fn annotations(file: &str) -> Result<Vec<Annotation>,glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let ext = poppler_ext::Document::new_from_file(file)?;
+    let mut r = vec![];
+    for i in 0 .. doc.get_n_pages() {
+        r.extend(page_annotations(&doc, &ext, i)?);
+    }
+    Ok(r)
+}
+
+pub fn to_markdown(file: &str, a: &[Annotation]) -> String {
+    let mut r = format!("## {}\n\n", file);
+    for x in a {
+        let who = match (&x.author, &x.date) {
+            (&Some(ref n), &Some(ref d)) => format!(" — {}, {}", n, d),
+            (&Some(ref n), &None) => format!(" — {}", n),
+            (&None, &Some(ref d)) => format!(" — {}", d),
+            _ => String::new()
+        };
+        r += &format!("- p. {} ({}){}\n", x.page + 1, x.kind, who);
+        if let Some(ref t) = x.text {
+            r += &format!("  > {}\n", t);
+        }
+        if let Some(ref c) = x.comment {
+            for line in c.lines() {
+                r += &format!("  {}\n", line);
+            }
+        }
+    }
+    r += "\n";
+    r
+}
+
+pub fn to_json(file: &str, a: &[Annotation]) -> Value {
+    json!({
+        "file": file,
+        "annotations": a.iter().map(|x| json!({
+            "page": x.page + 1,
+            "type": x.kind,
+            "text": x.text,
+            "comment": x.comment,
+            "author": x.author,
+            "date": x.date,
+        })).collect::<Vec<_>>()
+    })
+}
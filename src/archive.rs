@@ -0,0 +1,51 @@
+use crate::item::GlobalId;
+use crate::thesis::find_ci;
+
+fn token(s: &str) -> &str {
+    let s = s.trim_start_matches(|c: char| c.is_whitespace() || c == ':');
+    let end = s.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.')).unwrap_or(s.len());
+    s[..end].trim_end_matches('.')
+}
+
+// HAL front page: "HAL Id: hal-01234567", "https://hal.archives-ouvertes.fr/hal-01234567",
+// "Submitted on 12 Jan 2016", followed by the bilingual open access blurb
+pub fn is_hal_cover(text: &str) -> bool {
+    find_ci(text, "hal id").is_some() &&
+        (find_ci(text, "submitted on").is_some() || find_ci(text, "hal is a multi-disciplinary").is_some())
+}
+
+pub fn hal_id(text: &str) -> Option<GlobalId> {
+    let i = find_ci(text, "hal id")?;
+    let id = token(&text[i + "hal id".len()..]);
+    if id.contains('-') {
+        Some(GlobalId::HAL(String::from(id)))
+    }
+    else {
+        None
+    }
+}
+
+// CiteSeerX identifiers look like 10.1.1.123.4567
+fn citeseerx_token(s: &str) -> Option<&str> {
+    let prefix = "10.1.1.";
+    let mut from = 0;
+    while let Some(i) = s[from..].find(prefix) {
+        let start = from + i;
+        let rest = &s[start + prefix.len()..];
+        let n1 = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if n1 > 0 && rest[n1..].starts_with('.') {
+            let tail = &rest[n1 + 1..];
+            let n2 = tail.find(|c: char| !c.is_ascii_digit()).unwrap_or(tail.len());
+            if n2 > 0 {
+                return Some(&s[start .. start + prefix.len() + n1 + 1 + n2])
+            }
+        }
+        from = start + prefix.len();
+    }
+    None
+}
+
+pub fn citeseerx_id(text: &str) -> Option<GlobalId> {
+    find_ci(text, "citeseer")?;
+    citeseerx_token(text).map(|x| GlobalId::CITEER(String::from(x)))
+}
@@ -0,0 +1,296 @@
+// Minimal BibTeX reader: entries keep their byte span so a copy can be written back with edits
+
+use crate::graph::title_similarity;
+use crate::ident;
+use crate::item::{Author, GlobalId, Item};
+
+#[derive(Debug,Clone)]
+pub struct Entry {
+    pub kind: String,
+    pub key: String,
+    pub fields: Vec<(String, String)>,
+    // offset of the closing delimiter
+    close: usize,
+}
+
+fn skip_ws(b: &[u8], mut i: usize) -> usize {
+    while i < b.len() && (b[i] as char).is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+// index just past the delimiter matching b[i]
+fn balanced(b: &[u8], i: usize) -> Option<usize> {
+    let (open, close) = if b[i] == b'(' { (b'(', b')') } else { (b'{', b'}') };
+    let mut depth = 0;
+    for j in i .. b.len() {
+        if b[j] == open {
+            depth += 1;
+        }
+        else if b[j] == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(j + 1)
+            }
+        }
+    }
+    None
+}
+
+fn quoted(b: &[u8], i: usize) -> Option<usize> {
+    let mut depth = 0;
+    for j in i + 1 .. b.len() {
+        match b[j] {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b'"' if depth == 0 => return Some(j + 1),
+            _ => ()
+        }
+    }
+    None
+}
+
+// value parts joined with '#'; returns the value and the index after it
+fn value(s: &str, mut i: usize) -> Option<(String, usize)> {
+    let b = s.as_bytes();
+    let mut r = String::new();
+    loop {
+        i = skip_ws(b, i);
+        if i >= b.len() {
+            return None
+        }
+        if b[i] == b'{' {
+            let j = balanced(b, i)?;
+            r += &s[i+1..j-1];
+            i = j;
+        }
+        else if b[i] == b'"' {
+            let j = quoted(b, i)?;
+            r += &s[i+1..j-1];
+            i = j;
+        }
+        else {
+            let j = s[i..].find(|c: char| c == ',' || c == '#' || c == '}' || c == ')' || c.is_whitespace())
+                .map_or(b.len(), |x| i + x);
+            r += &s[i..j];
+            i = j;
+        }
+        i = skip_ws(b, i);
+        if i < b.len() && b[i] == b'#' {
+            i += 1;
+            continue
+        }
+        return Some((r, i))
+    }
+}
+
+// "article", "inproceedings", "comment"
+fn is_kind(s: &str) -> bool {
+    s.chars().next().map_or(false, |c| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// an entry starts at '@' followed by its kind and '{' or '('; an '@' in free text is skipped
+fn entry(s: &str, at: usize) -> Option<(Entry, usize)> {
+    let b = s.as_bytes();
+    let open = s[at..].find(|c: char| c == '{' || c == '(').map(|x| at + x)?;
+    let kind = s[at+1..open].trim();
+    if !is_kind(kind) {
+        return None
+    }
+    let kind = kind.to_lowercase();
+    let end = balanced(b, open)?;
+    if kind == "comment" || kind == "string" || kind == "preamble" {
+        return Some((Entry{kind, key: String::new(), fields: vec![], close: end - 1}, end))
+    }
+    let comma = s[open..end].find(',').map(|x| open + x)?;
+    let key = String::from(s[open+1..comma].trim());
+    let mut fields = vec![];
+    let mut i = comma + 1;
+    loop {
+        i = skip_ws(b, i);
+        if i >= end - 1 {
+            break
+        }
+        let eq = match s[i..end].find('=') {
+            Some(x) => i + x,
+            None => break
+        };
+        let name = s[i..eq].trim().to_lowercase();
+        let (v, j) = value(s, eq + 1)?;
+        fields.push((name, v));
+        i = skip_ws(b, j);
+        if i < b.len() && b[i] == b',' {
+            i += 1;
+        }
+    }
+    Some((Entry{kind, key, fields, close: end - 1}, end))
+}
+
+pub fn parse(s: &str) -> Vec<Entry> {
+    let mut r = vec![];
+    let mut i = 0;
+    while let Some(x) = s[i..].find('@') {
+        let at = i + x;
+        match entry(s, at) {
+            Some((e, end)) => {
+                if !e.key.is_empty() {
+                    r.push(e);
+                }
+                i = end;
+            },
+            None => i = at + 1
+        }
+    }
+    r
+}
+
+fn strip_braces(s: &str) -> String {
+    s.chars().filter(|&c| c != '{' && c != '}').collect::<String>()
+        .split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl Entry {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields.iter().find(|x| x.0 == name).map(|x| x.1.as_str())
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.get("title").map(strip_braces)
+    }
+
+    pub fn authors(&self) -> Vec<Author> {
+        match self.get("author") {
+            Some(a) => strip_braces(a).split(" and ")
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty() && *x != "others")
+                .map(Author::from_name)
+                .collect(),
+            None => vec![]
+        }
+    }
+
+    pub fn year(&self) -> Option<u32> {
+        self.get("year").and_then(|x| strip_braces(x).trim().parse::<u32>().ok())
+    }
+
+    pub fn ids(&self) -> Vec<GlobalId> {
+        let mut r = vec![];
+        if let Some(d) = self.get("doi") {
+            r.extend(ident::dois(d).into_iter().map(GlobalId::DOI));
+        }
+        let arxiv = self.get("archiveprefix").map_or(false, |x| x.eq_ignore_ascii_case("arxiv"));
+        if let Some(e) = self.get("eprint") {
+            if arxiv || self.get("archiveprefix").is_none() {
+                r.extend(ident::arxiv_ids(&format!("arXiv:{}", e.trim())).into_iter().map(GlobalId::ARXIV));
+            }
+        }
+        if let Some(i) = self.get("isbn") {
+            r.extend(ident::isbns(&format!("ISBN {}", i)).into_iter().map(GlobalId::ISBN));
+        }
+        r.into_iter().map(|x| x.normalize()).collect()
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Via {
+    Id,
+    Title,
+}
+
+// identifiers first, then title with a compatible year
+pub fn match_entry(entries: &[Entry], item: &Item) -> Option<(usize, Via)> {
+    for (i, e) in entries.iter().enumerate() {
+        if e.ids().iter().any(|x| item.own_ids().any(|y| x.same_work(y))) {
+            return Some((i, Via::Id))
+        }
+    }
+    if item.title.is_empty() {
+        return None
+    }
+    let mut best = None;
+    let mut best_score = 0.85;
+    for (i, e) in entries.iter().enumerate() {
+        let title = match e.title() {
+            Some(x) => x,
+            None => continue
+        };
+        if let (Some(a), Some(b)) = (e.year(), item.pub_date.map(|x| x.year)) {
+            if (a as i64 - b as i64).abs() > 1 {
+                continue
+            }
+        }
+        let score = title_similarity(&title, &item.title);
+        if score >= best_score {
+            best_score = score;
+            best = Some((i, Via::Title));
+        }
+    }
+    best
+}
+
+#[derive(Debug)]
+pub struct Conflict {
+    pub field: &'static str,
+    pub pdf: String,
+    pub bib: String,
+}
+
+fn last_names(a: &[Author]) -> Vec<String> {
+    let mut r = a.iter().map(|x| x.last_name.to_lowercase()).collect::<Vec<_>>();
+    r.sort();
+    r
+}
+
+pub fn conflicts(e: &Entry, item: &Item) -> Vec<Conflict> {
+    let mut r = vec![];
+    if let Some(t) = e.title() {
+        if !item.title.is_empty() && title_similarity(&t, &item.title) < 0.85 {
+            r.push(Conflict{field: "title", pdf: item.title.clone(), bib: t});
+        }
+    }
+    let a = e.authors();
+    if !a.is_empty() && !item.authors.is_empty() && last_names(&a) != last_names(&item.authors) {
+        let names = |x: &[Author]| x.iter().map(|x| x.last_name.clone()).collect::<Vec<_>>().join(", ");
+        r.push(Conflict{field: "author", pdf: names(&item.authors), bib: names(&a)});
+    }
+    if let (Some(y), Some(d)) = (e.year(), item.pub_date) {
+        if y != d.year {
+            r.push(Conflict{field: "year", pdf: d.year.to_string(), bib: y.to_string()});
+        }
+    }
+    for x in e.ids() {
+        let clash = item.own_ids().find(|y| {
+            std::mem::discriminant(&x) == std::mem::discriminant(*y) && !x.same_work(y)
+        });
+        if let Some(y) = clash {
+            r.push(Conflict{field: "id", pdf: format!("{:?}", y), bib: format!("{:?}", x)});
+        }
+    }
+    r
+}
+
+// copy of `src` with a file field added to each listed entry that lacks one; several files of
+// one entry go into the same field, separated by ';'
+pub fn with_files(src: &str, entries: &[Entry], files: &[(usize, String)]) -> String {
+    let mut edits: Vec<(usize, Vec<&str>)> = vec![];
+    for &(i, ref f) in files.iter().filter(|&&(i, _)| entries[i].get("file").is_none()) {
+        match edits.iter_mut().find(|x| x.0 == entries[i].close) {
+            Some(x) => x.1.push(f.as_str()),
+            None => edits.push((entries[i].close, vec![f.as_str()]))
+        }
+    }
+    edits.sort_by_key(|x| x.0);
+    let mut r = String::new();
+    let mut from = 0;
+    for (close, fs) in edits {
+        let body = src[from..close].trim_end();
+        let sep = if body.ends_with(',') { "" } else { "," };
+        r += body;
+        r += &format!("{}\n  file = {{{}}}\n", sep, fs.join(";"));
+        from = close;
+    }
+    r += &src[from..];
+    r
+}
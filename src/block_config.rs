@@ -0,0 +1,28 @@
+// Thresholds of the block segmenter in blocks.rs. Only plain values here so that zuzu.rs and
+// similar.rs with their own TT can use them too.
+
+#[derive(Debug,Clone)]
+pub struct Config {
+    // without a learned spacing, a gap above this share of the line height splits
+    // (split_by_distance in zuzu.rs)
+    pub gap_factor: f64,
+    // gaps above this many font sizes always split (magic1 of is_complete_order)
+    pub max_gap: f64,
+    // allowed excess over the learned spacing, in font sizes (magic2 of is_complete_order)
+    pub spacing_tolerance: f64,
+    // a line indented by this many font sizes starts a paragraph
+    pub indent: f64,
+    pub split_on_font: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            gap_factor: 0.4,
+            max_gap: 1.0,
+            spacing_tolerance: 0.3,
+            indent: 0.8,
+            split_on_font: true,
+        }
+    }
+}
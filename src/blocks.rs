@@ -0,0 +1,151 @@
+// Lines grouped into paragraphs and other blocks. Line spacing is learned per font from the
+// document; the thresholds split_by_distance and is_complete_order in zuzu.rs had fixed are in
+// block_config.rs.
+
+use std::collections::HashMap;
+
+use crate::equations::{equation_number, is_display, is_math_font};
+use crate::layout::{font_key, median, merge_boxes, BBox, Char, FontKey, TT};
+use crate::reading_order::{gutters, reading_order};
+
+pub use crate::block_config::Config;
+
+#[derive(Debug,Clone)]
+pub struct Block {
+    // indices into the page lines, in reading order
+    pub lines: Vec<usize>,
+    pub b_box: BBox,
+    pub text: String,
+    pub font_name: String,
+    pub font_size: f64,
+    // a display equation, never merged with prose
+    pub equation: bool,
+}
+
+// dominant gap between consecutive lines of the same font
+pub struct Spacing(HashMap<FontKey, f64>);
+
+fn same_font(a: &TT, b: &TT) -> bool {
+    a.font_name == b.font_name && (a.font_size - b.font_size).abs() < 0.05
+}
+
+fn overlaps(a: &BBox, b: &BBox) -> bool {
+    a.left < b.right && b.left < a.right
+}
+
+impl Spacing {
+    pub fn learn(pages: &[Vec<TT>]) -> Spacing {
+        let mut gaps: HashMap<(String, i64), Vec<f64>> = HashMap::new();
+        for p in pages {
+            let order = reading_order(p);
+            for w in order.windows(2) {
+                let (a, b) = (&p[w[0]], &p[w[1]]);
+                if !same_font(a, b) || !overlaps(&a.b_box, &b.b_box) || b.b_box.top < a.b_box.top {
+                    continue
+                }
+                let d = a.b_box.dist(&b.b_box);
+                // paragraph breaks and section gaps would skew the estimate
+                if d < 2.0 * a.font_size {
+                    gaps.entry(font_key(a)).or_insert_with(Vec::new).push(d);
+                }
+            }
+        }
+        Spacing(gaps.into_iter().filter_map(|(k, v)| median(v).map(|m| (k, m))).collect())
+    }
+
+    pub fn get(&self, t: &TT) -> Option<f64> {
+        self.0.get(&font_key(t)).cloned()
+    }
+}
+
+// share of each line's characters set in math fonts; `chars` from layout::page_chars
+pub fn math_shares(chars: &[Char], n: usize) -> Vec<f64> {
+    let mut math = vec![0usize; n];
+    let mut all = vec![0usize; n];
+    for ch in chars.iter().filter(|ch| ch.line < n && !ch.c.is_whitespace()) {
+        all[ch.line] += 1;
+        if is_math_font(&ch.font_name) {
+            math[ch.line] += 1;
+        }
+    }
+    math.iter().zip(all.iter()).map(|(&m, &a)| if a > 0 { m as f64 / a as f64 } else { 0.0 }).collect()
+}
+
+// display equation lines, judged against the column each line sits in
+pub fn display_lines(lines: &[TT], math: &[f64]) -> Vec<bool> {
+    let g = gutters(lines);
+    let left = lines.iter().map(|t| t.b_box.left).fold(std::f64::MAX, f64::min);
+    let right = lines.iter().map(|t| t.b_box.right).fold(std::f64::MIN, f64::max);
+    lines.iter().enumerate().map(|(i, t)| {
+        let c = (t.b_box.left + t.b_box.right) / 2.0;
+        let column = (g.iter().filter(|x| x.1 <= c).map(|x| x.1).fold(left, f64::max),
+                      g.iter().filter(|x| x.0 >= c).map(|x| x.0).fold(right, f64::min));
+        let size = if t.font_size > 0.0 { t.font_size } else { t.b_box.width() };
+        is_display(&t.text, math.get(i).cloned().unwrap_or(0.0), t.b_box.left, t.b_box.right, column, size)
+    }).collect()
+}
+
+fn breaks(cfg: &Config, spacing: &Spacing, len: usize, prev: &TT, t: &TT, eq: (bool, bool)) -> bool {
+    if cfg.split_on_font && !same_font(prev, t) {
+        return true
+    }
+    // equations stand apart from prose, and a numbered one ends there
+    if eq.0 != eq.1 || (eq.0 && equation_number(&prev.text).is_some()) {
+        return true
+    }
+    // next column, or back up the page
+    if !overlaps(&prev.b_box, &t.b_box) || t.b_box.top < prev.b_box.top {
+        return true
+    }
+    let gap = prev.b_box.dist(&t.b_box);
+    let size = if prev.font_size > 0.0 { prev.font_size } else { prev.b_box.width() };
+    if gap > cfg.max_gap * size {
+        return true
+    }
+    let limit = match spacing.get(prev) {
+        Some(s) => s + cfg.spacing_tolerance * size,
+        None => cfg.gap_factor * prev.b_box.width()
+    };
+    if gap > limit {
+        return true
+    }
+    // first-line indent; after a single line it may be a hanging indent instead
+    len > 1 && t.b_box.left - prev.b_box.left > cfg.indent * size
+}
+
+// `display` flags the lines of display equations, see display_lines; it may be empty
+pub fn page_blocks(lines: &[TT], display: &[bool], spacing: &Spacing, cfg: &Config) -> Vec<Block> {
+    let order = reading_order(lines).into_iter().filter(|&i| !lines[i].text.trim().is_empty()).collect::<Vec<_>>();
+    let eq = |i: usize| display.get(i).cloned().unwrap_or(false);
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for i in order {
+        let split = match groups.last() {
+            Some(g) => {
+                let prev = g[g.len() - 1];
+                breaks(cfg, spacing, g.len(), &lines[prev], &lines[i], (eq(prev), eq(i)))
+            },
+            None => true
+        };
+        if split {
+            groups.push(vec![i]);
+        }
+        else {
+            groups.last_mut().unwrap().push(i);
+        }
+    }
+    groups.into_iter().map(|g| Block {
+        b_box: merge_boxes(g.iter().map(|&i| &lines[i].b_box)),
+        text: g.iter().map(|&i| lines[i].text.as_str()).collect::<Vec<_>>().join("\n"),
+        font_name: lines[g[0]].font_name.clone(),
+        font_size: lines[g[0]].font_size,
+        equation: eq(g[0]),
+        lines: g,
+    }).collect()
+}
+
+pub fn document_blocks(pages: &[Vec<TT>], display: &[Vec<bool>], cfg: &Config) -> Vec<Vec<Block>> {
+    let spacing = Spacing::learn(pages);
+    pages.iter().enumerate().map(|(i, p)| {
+        page_blocks(p, display.get(i).map_or(&[][..], |x| &x[..]), &spacing, cfg)
+    }).collect()
+}
@@ -0,0 +1,257 @@
+// Crossref works metadata from a JSON-lines snapshot or a local server with the REST API shape
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+
+use serde_json::Value;
+
+use crate::graph::title_similarity;
+use crate::item::{Author, Date, Disagreement, GlobalId, Item, JournalRef};
+
+pub enum Source {
+    Snapshot(HashMap<String, Value>),
+    // "host:port" and the path prefix, GET {prefix}/works/{doi}
+    Http(String, String),
+}
+
+// a line is either a bare work or an API response wrapping it in "message"
+fn work(v: Value) -> Value {
+    match v {
+        Value::Object(mut m) => {
+            match m.remove("message") {
+                Some(x) => x,
+                None => Value::Object(m)
+            }
+        },
+        x => x
+    }
+}
+
+fn doi_key(doi: &str) -> String {
+    doi.trim().to_lowercase()
+}
+
+// "10.1000/a<b>" -> "10.1000%2Fa%3Cb%3E"; a DOI may hold any printable character
+fn percent_encode(s: &str) -> String {
+    let mut r = String::new();
+    for b in s.trim().bytes() {
+        match b {
+            b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9' | b'-' | b'.' | b'_' | b'~' => r.push(b as char),
+            _ => r += &format!("%{:02X}", b)
+        }
+    }
+    r
+}
+
+impl Source {
+    // "http://localhost:8080", "http://localhost:8080/crossref" or a path to the snapshot
+    pub fn open(s: &str) -> io::Result<Source> {
+        if s.starts_with("http://") {
+            let rest = s["http://".len()..].trim_end_matches('/');
+            let (host, prefix) = match rest.find('/') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, "")
+            };
+            return Ok(Source::Http(String::from(host), String::from(prefix)))
+        }
+        let mut works = HashMap::new();
+        let f = io::BufReader::new(std::fs::File::open(s)?);
+        for line in f.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            let v = work(serde_json::from_str(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?);
+            if let Some(doi) = v.get("DOI").and_then(|x| x.as_str()) {
+                works.insert(doi_key(doi), v.clone());
+            }
+        }
+        Ok(Source::Snapshot(works))
+    }
+
+    pub fn lookup(&self, doi: &str) -> io::Result<Option<Value>> {
+        match *self {
+            Source::Snapshot(ref works) => Ok(works.get(&doi_key(doi)).cloned()),
+            Source::Http(ref host, ref prefix) => http_get(host, &format!("{}/works/{}", prefix, percent_encode(doi)))
+        }
+    }
+}
+
+// plain HTTP/1.0 so the body is neither chunked nor kept alive
+fn http_get(host: &str, path: &str) -> io::Result<Option<Value>> {
+    let mut s = std::net::TcpStream::connect(host)?;
+    write!(s, "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n", path, host)?;
+    let mut resp = String::new();
+    s.read_to_string(&mut resp)?;
+    let status = resp.split_whitespace().nth(1).unwrap_or("");
+    if status == "404" {
+        return Ok(None)
+    }
+    if status != "200" {
+        return Err(io::Error::new(io::ErrorKind::Other, format!("{}: HTTP {}", path, status)))
+    }
+    let body = match resp.find("\r\n\r\n") {
+        Some(i) => &resp[i+4..],
+        None => return Ok(None)
+    };
+    serde_json::from_str(body)
+        .map(|x| Some(work(x)))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+fn first_str(v: &Value, key: &str) -> Option<String> {
+    match v.get(key) {
+        Some(&Value::String(ref s)) => Some(s.clone()),
+        Some(&Value::Array(ref a)) => a.iter().filter_map(|x| x.as_str()).next().map(String::from),
+        _ => None
+    }.map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|s| !s.is_empty())
+}
+
+fn authors(v: &Value) -> Vec<Author> {
+    let list = match v.get("author").and_then(|x| x.as_array()) {
+        Some(x) => x,
+        None => return vec![]
+    };
+    list.iter().filter_map(|a| {
+        let family = a.get("family").and_then(|x| x.as_str())?;
+        let given = a.get("given").and_then(|x| x.as_str()).unwrap_or("");
+        Some(Author{first_name: String::from(given), last_name: String::from(family)})
+    }).collect()
+}
+
+// print date first, then online, then the catch-all "issued"
+fn date(v: &Value) -> Option<Date> {
+    for key in ["published-print", "published-online", "issued"].iter() {
+        let parts = v.get(*key)
+            .and_then(|x| x.get("date-parts"))
+            .and_then(|x| x.get(0))
+            .and_then(|x| x.as_array());
+        let parts = match parts {
+            Some(x) => x.iter().filter_map(|x| x.as_u64()).map(|x| x as u32).collect::<Vec<_>>(),
+            None => continue
+        };
+        if let Some(&year) = parts.get(0) {
+            return Some(Date{year, month: parts.get(1).cloned(), day: parts.get(2).cloned()})
+        }
+    }
+    None
+}
+
+fn journal(v: &Value) -> Option<JournalRef> {
+    let name = first_str(v, "container-title")?;
+    let mut r = JournalRef::default();
+    r.journal = name;
+    r.volume = first_str(v, "volume");
+    r.issue = first_str(v, "issue");
+    if let Some(p) = first_str(v, "page") {
+        let mut it = p.splitn(2, '-');
+        r.first_page = it.next().map(String::from);
+        r.last_page = it.next().map(String::from);
+    }
+    r.year = date(v).map(|x| x.year);
+    Some(r)
+}
+
+fn words(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase())
+        .filter(|x| x != "of" && x != "the" && x != "and" && x != "for")
+        .collect()
+}
+
+// "J. Funct. Anal." against "Journal of Functional Analysis"
+fn same_journal(a: &str, b: &str) -> bool {
+    let (a, b) = (words(a), words(b));
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    short.len() == long.len() && !short.is_empty()
+        && short.iter().zip(long.iter()).all(|(x, y)| y.starts_with(x.as_str()) || x.starts_with(y.as_str()))
+}
+
+fn same_authors(a: &[Author], b: &[Author]) -> bool {
+    let norm = |x: &[Author]| {
+        let mut r = x.iter().map(|a| a.last_name.to_lowercase()).collect::<Vec<_>>();
+        r.sort();
+        r
+    };
+    norm(a) == norm(b)
+}
+
+fn author_list(a: &[Author]) -> String {
+    a.iter().map(|x| format!("{}, {}", x.last_name, x.first_name)).collect::<Vec<_>>().join("; ")
+}
+
+fn same_date(a: &Date, b: &Date) -> bool {
+    a.year == b.year && (a.month.is_none() || b.month.is_none() || a.month == b.month)
+}
+
+pub fn item_doi(item: &Item) -> Option<String> {
+    let mut dois = item.own_ids().filter_map(|x| match *x {
+        GlobalId::DOI(ref d) => Some(d.clone()),
+        _ => None
+    });
+    match item.primary_id() {
+        Some(&GlobalId::DOI(ref d)) => Some(d.clone()),
+        _ => dois.next()
+    }
+}
+
+fn disagree(field: &'static str, extracted: String, crossref: String) -> Disagreement {
+    Disagreement{field, extracted, source: "crossref", value: crossref}
+}
+
+// missing fields are filled in, conflicting ones are kept as extracted and recorded on the item
+pub fn reconcile(item: &mut Item, v: &Value) {
+    let mut r = vec![];
+    if let Some(title) = first_str(v, "title") {
+        if item.title.is_empty() {
+            item.title = title;
+        }
+        else if title_similarity(&item.title, &title) < 0.85 {
+            r.push(disagree("title", item.title.clone(), title));
+        }
+    }
+    let a = authors(v);
+    if !a.is_empty() {
+        if item.authors.is_empty() {
+            item.authors = a;
+        }
+        else if !same_authors(&item.authors, &a) {
+            r.push(disagree("authors", author_list(&item.authors), author_list(&a)));
+        }
+    }
+    if let Some(j) = journal(v) {
+        let short = first_str(v, "short-container-title");
+        let differs = match item.journal {
+            None => {
+                item.journal = Some(j.clone());
+                None
+            },
+            Some(ref mut x) => {
+                if x.volume.is_none() { x.volume = j.volume.clone() }
+                if x.issue.is_none() { x.issue = j.issue.clone() }
+                if x.first_page.is_none() { x.first_page = j.first_page.clone() }
+                if x.last_page.is_none() { x.last_page = j.last_page.clone() }
+                if x.year.is_none() { x.year = j.year }
+                let same = same_journal(&x.journal, &j.journal)
+                    || short.as_ref().map_or(false, |s| same_journal(&x.journal, s));
+                if same { None } else { Some(x.journal.clone()) }
+            }
+        };
+        if let Some(x) = differs {
+            r.push(disagree("journal", x, j.journal));
+        }
+    }
+    if let Some(d) = date(v) {
+        match item.pub_date {
+            None => item.pub_date = Some(d),
+            Some(x) => {
+                if !same_date(&x, &d) {
+                    r.push(disagree("date", x.to_string(), d.to_string()));
+                }
+            }
+        }
+    }
+    item.disagreements.extend(r);
+}
@@ -0,0 +1,72 @@
+// Display equations: lines set mostly in math fonts, centred in their column, or ending in an
+// equation number like "(3.1)". Only plain values here so that zuzu.rs with its own TT can
+// use it too; blocks.rs applies it to layout lines.
+
+// subset prefix "ABCDEF+" removed
+fn base_name(font: &str) -> String {
+    let f = match font.find('+') {
+        Some(i) if i == 6 => &font[i + 1 ..],
+        _ => font
+    };
+    f.to_lowercase()
+}
+
+pub fn is_math_font(font: &str) -> bool {
+    let f = base_name(font);
+    ["cmmi", "cmsy", "cmex", "msam", "msbm", "eufm", "rsfs", "lmmath", "latinmodernmath",
+     "stixmath", "stixtwomath", "stixsize", "stixnonuni", "stixvariants", "stixintegrals",
+     "xitsmath", "cambriamath", "cambria math", "asanamath", "mathjax"]
+        .iter().any(|x| f.contains(x))
+}
+
+// "(3.1)", "(12)", "(A.2)", "(4.3b)" at the end of a line -> "3.1"; not years like "(2019)"
+pub fn equation_number(s: &str) -> Option<&str> {
+    let s = s.trim_end();
+    if !s.ends_with(')') {
+        return None
+    }
+    let open = s.rfind('(')?;
+    if open > 0 && !s[.. open].ends_with(char::is_whitespace) {
+        return None
+    }
+    let n = &s[open + 1 .. s.len() - 1];
+    let parts = n.split('.').collect::<Vec<_>>();
+    let ok = n.chars().any(|c| c.is_ascii_digit())
+        && n.chars().next().map_or(false, |c| c.is_ascii_digit() || c.is_ascii_uppercase())
+        && parts.iter().all(|p| !p.is_empty() && p.len() <= 3 && p.chars().all(|c| c.is_ascii_alphanumeric()));
+    if ok {
+        Some(n)
+    }
+    else {
+        None
+    }
+}
+
+// share of words that are plain words of three or more letters
+fn prose(s: &str) -> f64 {
+    let ws = s.split_whitespace().collect::<Vec<_>>();
+    if ws.is_empty() {
+        return 0.0
+    }
+    let n = ws.iter().filter(|w| {
+        let w = w.trim_matches(|c: char| c.is_ascii_punctuation());
+        w.chars().count() >= 3 && w.chars().all(|c| c.is_alphabetic())
+    }).count();
+    n as f64 / ws.len() as f64
+}
+
+// `math` is the share of the line's characters in math fonts, `column` its column's left and
+// right edge
+pub fn is_display(text: &str, math: f64, left: f64, right: f64, column: (f64, f64), size: f64) -> bool {
+    if text.trim().is_empty() {
+        return false
+    }
+    let lm = left - column.0;
+    let rm = column.1 - right;
+    let indented = lm > 2.0 * size;
+    let centred = indented && rm > 2.0 * size && (lm - rm).abs() < (0.1 * (column.1 - column.0)).max(3.0 * size);
+    if equation_number(text).is_some() {
+        return indented || math >= 0.2
+    }
+    (centred && math >= 0.3) || (math >= 0.6 && prose(text) < 0.5)
+}
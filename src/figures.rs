@@ -0,0 +1,145 @@
+// Figures as the empty stretch of a column next to a "Fig. N" caption: no body text lines,
+// at most small labels. Stretches taller than UNCAPTIONED of the page count as figures too.
+
+use crate::layout::{BBox, TT};
+use crate::normalize::normalize_line;
+use crate::reading_order::gutters;
+
+#[derive(Debug,Clone)]
+pub struct Figure {
+    pub page: usize,
+    pub b_box: BBox,
+    // "3" of "Figure 3"
+    pub number: Option<String>,
+    pub caption: Option<String>,
+}
+
+// shorter areas are rules or spacing
+const MIN_HEIGHT: f64 = 3.0;
+const UNCAPTIONED: f64 = 0.2;
+
+// "Fig. 3", "Figure 3:", "FIGURE 3.", "Fig 3a" -> "3", "3a"
+pub fn caption_number(s: &str) -> Option<String> {
+    let mut it = s.trim().split_whitespace();
+    let first = it.next()?;
+    let (word, rest) = match first.find(|c: char| c.is_ascii_digit()) {
+        // "Fig.3"
+        Some(i) => (&first[.. i], &first[i ..]),
+        None => (first, it.next()?)
+    };
+    match word.trim_end_matches('.').to_lowercase().as_str() {
+        "fig" | "figure" => (),
+        _ => return None
+    }
+    let n = rest.trim_end_matches(|c: char| c == '.' || c == ':' || c == ')');
+    if n.chars().next().map_or(false, |c| c.is_ascii_digit()) && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') {
+        Some(String::from(n))
+    }
+    else {
+        None
+    }
+}
+
+// "Figure 3:" rather than "Figure 3 shows" at the start of a body line
+fn labelled(s: &str) -> bool {
+    s.split_whitespace().take(2).any(|w| w.chars().any(|c| c.is_ascii_digit()) && (w.ends_with('.') || w.ends_with(':') || w.ends_with('|')))
+}
+
+fn overlaps(t: &TT, span: (f64, f64)) -> bool {
+    t.b_box.left < span.1 && span.0 < t.b_box.right
+}
+
+// the column of a line, or the whole text width for lines across a gutter
+fn span(t: &TT, g: &[(f64, f64)], left: f64, right: f64) -> (f64, f64) {
+    if g.iter().any(|x| t.b_box.left < x.0 && t.b_box.right > x.1) {
+        return (left, right)
+    }
+    let c = (t.b_box.left + t.b_box.right) / 2.0;
+    let l = g.iter().filter(|x| x.1 <= c).map(|x| x.1).fold(left, f64::max);
+    let r = g.iter().filter(|x| x.0 >= c).map(|x| x.0).fold(right, f64::min);
+    (l, r)
+}
+
+fn by_top(a: &&TT, b: &&TT) -> std::cmp::Ordering {
+    a.b_box.top.partial_cmp(&b.b_box.top).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+pub fn page_figures(page: usize, lines: &[TT], body_size: f64, page_height: f64) -> Vec<Figure> {
+    let g = gutters(lines);
+    let lines = lines.iter().filter(|t| !t.text.trim().is_empty()).collect::<Vec<_>>();
+    if lines.is_empty() {
+        return vec![]
+    }
+    let left = lines.iter().map(|t| t.b_box.left).fold(std::f64::MAX, f64::min);
+    let right = lines.iter().map(|t| t.b_box.right).fold(std::f64::MIN, f64::max);
+    let top = lines.iter().map(|t| t.b_box.top).fold(std::f64::MAX, f64::min);
+    let bottom = lines.iter().map(|t| t.b_box.bottom).fold(std::f64::MIN, f64::max);
+    let is_caption = |t: &TT| {
+        caption_number(&t.text).is_some() && (labelled(&t.text) || (t.font_size - body_size).abs() > 0.3)
+    };
+    // labels inside figures are small or short
+    let is_text = |t: &TT, s: (f64, f64)| {
+        t.font_size >= body_size - 0.5 && t.b_box.length() > 0.5 * (s.1 - s.0) && !is_caption(t)
+    };
+    let mut r: Vec<Figure> = vec![];
+    let mut captions = lines.iter().filter(|t| is_caption(t)).cloned().collect::<Vec<_>>();
+    captions.sort_by(by_top);
+    for c in &captions {
+        let s = span(c, &g, left, right);
+        let mut col = lines.iter().filter(|t| overlaps(t, s)).cloned().collect::<Vec<_>>();
+        col.sort_by(by_top);
+        // the caption runs on in lines of its size
+        let mut text = vec![c.text.trim()];
+        let mut end = c.b_box.bottom;
+        for t in col.iter().filter(|t| t.b_box.top > c.b_box.top) {
+            if t.b_box.top - end > 0.5 * c.font_size || (t.font_size - c.font_size).abs() > 0.5 || is_caption(t) {
+                break
+            }
+            text.push(t.text.trim());
+            end = t.b_box.bottom;
+        }
+        // body text or another caption bounds the figure
+        let stop = |t: &TT| is_text(t, s) || is_caption(t);
+        let above = col.iter().filter(|t| t.b_box.bottom <= c.b_box.top && stop(t))
+            .map(|t| t.b_box.bottom).fold(top, f64::max);
+        let below = col.iter().filter(|t| t.b_box.top >= end && stop(t))
+            .map(|t| t.b_box.top).fold(bottom, f64::min);
+        let (y1, y2) = if c.b_box.top - above >= MIN_HEIGHT * body_size {
+            (above, c.b_box.top)
+        }
+        else if below - end >= MIN_HEIGHT * body_size {
+            (end, below)
+        }
+        else {
+            continue
+        };
+        r.push(Figure {
+            page,
+            b_box: BBox{top: y1, left: s.0, bottom: y2, right: s.1},
+            number: caption_number(&c.text),
+            caption: Some(normalize_line(&text.join(" "))),
+        });
+    }
+    // tall empty stretches of a column without a caption
+    let mut spans = vec![];
+    let mut x = left;
+    for gt in &g {
+        spans.push((x, gt.0));
+        x = gt.1;
+    }
+    spans.push((x, right));
+    for s in spans {
+        let mut col = lines.iter().filter(|t| overlaps(t, s) && is_text(t, s)).cloned().collect::<Vec<_>>();
+        col.sort_by(by_top);
+        let mut y = top;
+        for t in col.iter().map(|t| (t.b_box.top, t.b_box.bottom)).chain(std::iter::once((bottom, bottom))) {
+            let taken = r.iter().any(|f| f.b_box.left < s.1 && s.0 < f.b_box.right && f.b_box.top < t.0 && y < f.b_box.bottom);
+            if t.0 - y > UNCAPTIONED * page_height && !taken {
+                r.push(Figure{page, b_box: BBox{top: y, left: s.0, bottom: t.0, right: s.1}, number: None, caption: None});
+            }
+            y = y.max(t.1);
+        }
+    }
+    r.sort_by(|a, b| a.b_box.top.partial_cmp(&b.b_box.top).unwrap_or(std::cmp::Ordering::Equal));
+    r
+}
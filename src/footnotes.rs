@@ -0,0 +1,166 @@
+// Footnotes: the run of small-font lines at the bottom of a page, set off by a gap (the rule
+// above them is not text) and opened by a marker, linked to the superscripts of the same
+// marker in the text above.
+
+use crate::layout::{median, merge_boxes, BBox, Char, TT};
+
+#[derive(Debug,Clone)]
+pub struct Ref {
+    pub page: usize,
+    pub b_box: BBox,
+}
+
+#[derive(Debug,Clone)]
+pub struct Footnote {
+    pub page: usize,
+    pub marker: String,
+    pub text: String,
+    pub b_box: BBox,
+    // indices into the page lines
+    pub lines: Vec<usize>,
+    pub refs: Vec<Ref>,
+}
+
+const SYMBOLS: &str = "*∗†‡§¶‖#⋆";
+// footnote text is at most this share of the body font size
+const SMALL: f64 = 0.92;
+
+// "1 This work", "1This work", "∗ Corresponding author", "a Department of" -> marker and text
+pub fn marker(s: &str) -> Option<(String, &str)> {
+    let s = s.trim_start();
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    let symbols = s.chars().take_while(|&c| SYMBOLS.contains(c)).count();
+    let (m, rest) = if digits > 0 && digits <= 3 {
+        (&s[.. digits], &s[digits ..])
+    }
+    else if symbols > 0 && symbols <= 3 {
+        let end = s.char_indices().nth(symbols).map_or(s.len(), |(i, _)| i);
+        (&s[.. end], &s[end ..])
+    }
+    else {
+        // a single letter marker needs the space after it
+        let mut it = s.chars();
+        match (it.next(), it.next()) {
+            (Some(c), Some(' ')) if c.is_ascii_lowercase() => (&s[.. 1], &s[1 ..]),
+            _ => return None
+        }
+    };
+    let rest = rest.trim_start();
+    if rest.chars().next().map_or(false, |c| c.is_alphabetic() || c == '(' || c == '"') {
+        Some((String::from(m), rest))
+    }
+    else {
+        None
+    }
+}
+
+fn by_top(lines: &[TT], a: usize, b: usize) -> std::cmp::Ordering {
+    lines[a].b_box.top.partial_cmp(&lines[b].b_box.top).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+// footnotes of one page, without refs; `lines` without running heads and page numbers
+pub fn page_footnotes(page: usize, lines: &[TT], body_size: f64) -> Vec<Footnote> {
+    let mut idx = (0 .. lines.len()).filter(|&i| !lines[i].text.trim().is_empty()).collect::<Vec<_>>();
+    idx.sort_by(|&a, &b| by_top(lines, a, b));
+    let small = |i: usize| lines[i].font_size > 0.0 && lines[i].font_size <= SMALL * body_size;
+    let start = idx.len() - idx.iter().rev().take_while(|&&i| small(i)).count();
+    // the zone opens with a marker; later small lines at the bottom may be part of it
+    let start = match (start .. idx.len()).find(|&k| marker(&lines[idx[k]].text).is_some()) {
+        Some(k) => k,
+        None => return vec![]
+    };
+    if start > 0 {
+        let (above, first) = (&lines[idx[start - 1]], &lines[idx[start]]);
+        if first.b_box.top - above.b_box.bottom < 0.8 * body_size && small(idx[start - 1]) {
+            return vec![]
+        }
+    }
+    let zone = &idx[start ..];
+    let left = zone.iter().map(|&i| lines[i].b_box.left).fold(std::f64::MAX, f64::min);
+    let mut r: Vec<Footnote> = vec![];
+    for &i in zone {
+        let t = &lines[i];
+        // a marker further in than the zone's left edge is inside a sentence wrapped from above
+        let opens = match marker(&t.text) {
+            Some(m) if t.b_box.left - left < 2.0 * t.font_size || r.is_empty() => Some(m),
+            _ => None
+        };
+        match opens {
+            Some((m, rest)) => r.push(Footnote {
+                page,
+                marker: m,
+                text: String::from(rest.trim()),
+                b_box: t.b_box.clone(),
+                lines: vec![i],
+                refs: vec![],
+            }),
+            None => {
+                let f = r.last_mut().unwrap();
+                f.text.push('\n');
+                f.text.push_str(t.text.trim());
+                f.b_box = merge_boxes([f.b_box.clone(), t.b_box.clone()].iter());
+                f.lines.push(i);
+            }
+        }
+    }
+    r
+}
+
+// raised small characters, split at commas: "1,2" -> "1", "2"; `chars` from layout::page_chars
+pub fn superscripts(chars: &[Char]) -> Vec<(String, BBox)> {
+    let mut r = vec![];
+    let mut from = 0;
+    while from < chars.len() {
+        let line = chars[from].line;
+        let to = from + chars[from ..].iter().take_while(|c| c.line == line).count();
+        let cs = &chars[from .. to];
+        let text = cs.iter().filter(|c| !c.c.is_whitespace()).collect::<Vec<_>>();
+        if text.len() > 2 {
+            let size = median(text.iter().map(|c| c.font_size).collect()).unwrap_or(0.0);
+            let base = median(text.iter().map(|c| c.b_box.bottom).collect()).unwrap_or(0.0);
+            let raised = |c: &Char| {
+                !c.c.is_whitespace() && c.font_size < 0.85 * size && c.b_box.bottom < base - 0.15 * size
+            };
+            let mut k = 0;
+            while k < cs.len() {
+                if !raised(&cs[k]) {
+                    k += 1;
+                    continue
+                }
+                let n = cs[k ..].iter().take_while(|c| raised(c)).count();
+                for part in cs[k .. k + n].split(|c| c.c == ',') {
+                    if !part.is_empty() {
+                        r.push((part.iter().map(|c| c.c).collect(), merge_boxes(part.iter().map(|c| &c.b_box))));
+                    }
+                }
+                k += n;
+            }
+        }
+        from = to;
+    }
+    r
+}
+
+// refs from the superscripts above the footnotes of the same page
+pub fn link(notes: &mut [Footnote], page: usize, sups: &[(String, BBox)]) {
+    let top = notes.iter().filter(|f| f.page == page).map(|f| f.b_box.top).fold(std::f64::MAX, f64::min);
+    for (m, b) in sups {
+        if b.top >= top - 1.0 {
+            continue
+        }
+        if let Some(f) = notes.iter_mut().find(|f| f.page == page && f.marker == *m) {
+            f.refs.push(Ref{page, b_box: b.clone()});
+        }
+    }
+}
+
+// small raised markers dropped from a line; `sizes` are the font sizes of its characters
+pub fn strip_markers(s: &str, sizes: &[f64]) -> String {
+    if sizes.is_empty() {
+        return String::from(s)
+    }
+    let size = median(sizes.to_vec()).unwrap_or(0.0);
+    s.chars().zip(sizes.iter().chain(std::iter::repeat(&size))).filter(|&(c, &z)| {
+        !(z < 0.85 * size && (c.is_ascii_alphanumeric() || SYMBOLS.contains(c) || c == ','))
+    }).map(|(c, _)| c).collect()
+}
@@ -0,0 +1,139 @@
+// Page furniture: running heads, footers and page numbers, found as lines that repeat at the
+// same height across pages once digits are ignored, and numbers that count on from page to page
+
+use crate::layout::TT;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Role {
+    Body,
+    Header,
+    Footer,
+    PageNumber,
+}
+
+// lines from each edge of the page that may be furniture
+const EDGE_LINES: usize = 3;
+// vertical tolerance in points
+const SAME_HEIGHT: f64 = 3.0;
+
+// "Downloaded from ... on 12 May 2020" and "... on 3 June 2021" give the same key
+fn key(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_digit() { '#' } else { c.to_lowercase().next().unwrap_or(c) })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// page numbers further apart are not compared
+const SEQUENCE_PAGES: usize = 2;
+
+static ROMAN: [(u32, &str); 13] = [(1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"),
+                                   (50, "l"), (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i")];
+
+// "xii" -> 12; only numerals written the usual way, so "mid" and "civil" are not
+fn roman(s: &str) -> Option<u32> {
+    let mut n = 0;
+    let mut rest = s;
+    for &(v, x) in ROMAN.iter() {
+        while rest.starts_with(x) {
+            n += v;
+            rest = &rest[x.len()..];
+        }
+    }
+    if s.is_empty() || !rest.is_empty() {
+        return None
+    }
+    let mut back = String::new();
+    let mut m = n;
+    for &(v, x) in ROMAN.iter() {
+        while m >= v {
+            back.push_str(x);
+            m -= v;
+        }
+    }
+    if back == s { Some(n) } else { None }
+}
+
+// "12", "- 12 -", "xii", "Page 3", "Page 3 of 20" -> the number
+pub fn page_number(s: &str) -> Option<u32> {
+    let s = s.trim().trim_matches(|c: char| c == '-' || c == '–' || c.is_whitespace()).to_lowercase();
+    let s = if s.starts_with("page ") { s[5..].trim().to_string() } else { s };
+    let first = s.split(" of ").next().unwrap_or("").trim();
+    if !first.is_empty() && first.len() <= 4 && first.chars().all(|c| c.is_ascii_digit()) {
+        first.parse().ok()
+    }
+    else {
+        roman(first)
+    }
+}
+
+// indices of the top and bottom lines of a page
+pub fn edges(lines: &[TT]) -> Vec<usize> {
+    let mut idx = (0 .. lines.len()).filter(|&i| !lines[i].text.trim().is_empty()).collect::<Vec<_>>();
+    idx.sort_by(|&a, &b| lines[a].b_box.top.partial_cmp(&lines[b].b_box.top).unwrap_or(std::cmp::Ordering::Equal));
+    let n = idx.len();
+    if n <= 2 * EDGE_LINES {
+        return idx
+    }
+    let mut r = idx[.. EDGE_LINES].to_vec();
+    r.extend_from_slice(&idx[n - EDGE_LINES ..]);
+    r
+}
+
+pub fn roles(pages: &[Vec<TT>]) -> Vec<Vec<Role>> {
+    let mut r = pages.iter().map(|p| vec![Role::Body; p.len()]).collect::<Vec<_>>();
+    let cand = pages.iter().map(|p| edges(p)).collect::<Vec<_>>();
+    // the middle of the text area decides between header and footer
+    let middle = |p: &[TT]| {
+        let top = p.iter().map(|x| x.b_box.top).fold(std::f64::MAX, f64::min);
+        let bottom = p.iter().map(|x| x.b_box.bottom).fold(std::f64::MIN, f64::max);
+        (top + bottom) / 2.0
+    };
+    let numbers = pages.iter().zip(cand.iter()).map(|(p, c)| {
+        c.iter().filter_map(|&i| page_number(&p[i].text).map(|n| (i, n))).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+    let need = std::cmp::max(2, pages.len() / 4);
+    for (pi, p) in pages.iter().enumerate() {
+        let mid = middle(p);
+        for &i in &cand[pi] {
+            let t = &p[i];
+            // a number is a page number when a nearby page counts on from it at the same height
+            if let Some(n) = page_number(&t.text) {
+                let counts = numbers.iter().enumerate().any(|(pj, ns)| {
+                    pj != pi && (pj as i64 - pi as i64).abs() <= SEQUENCE_PAGES as i64 && ns.iter().any(|&(j, m)| {
+                        (pages[pj][j].b_box.top - t.b_box.top).abs() <= SAME_HEIGHT
+                            && m as i64 - n as i64 == pj as i64 - pi as i64
+                    })
+                });
+                if counts {
+                    r[pi][i] = Role::PageNumber;
+                    continue
+                }
+            }
+            let k = key(&t.text);
+            let seen = pages.iter().enumerate().filter(|&(pj, q)| {
+                pj == pi || cand[pj].iter().any(|&j| {
+                    (q[j].b_box.top - t.b_box.top).abs() <= SAME_HEIGHT && key(&q[j].text) == k
+                })
+            }).count();
+            if pages.len() > 1 && seen >= need {
+                r[pi][i] = if t.b_box.top < mid { Role::Header } else { Role::Footer };
+            }
+        }
+    }
+    r
+}
+
+// the entries of `v` (one per page line) that belong to body lines
+pub fn keep_body<T: Clone>(roles: &[Vec<Role>], v: &[Vec<T>]) -> Vec<Vec<T>> {
+    roles.iter().zip(v.iter()).map(|(r, p)| {
+        p.iter().zip(r.iter()).filter(|&(_, &x)| x == Role::Body).map(|(t, _)| t.clone()).collect()
+    }).collect()
+}
+
+// the body lines of every page
+pub fn strip(pages: &[Vec<TT>]) -> Vec<Vec<TT>> {
+    keep_body(&roles(pages), pages)
+}
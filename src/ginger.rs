@@ -3,10 +3,44 @@ extern crate glib;
 extern crate glib_sys;
 #[macro_use] extern crate clap;
 extern crate walkdir;
+extern crate xml;
+extern crate serde_json;
+extern crate inotify;
+extern crate sha2;
+extern crate unicode_normalization;
 
 mod item;
+mod thesis;
+mod archive;
+mod preprint;
+mod ident;
+mod poppler_ext;
+mod journal;
+mod layout;
+mod references;
+mod graph;
+mod oai;
+mod crossref;
+mod bibtex;
+mod search;
+mod watch;
+mod organize;
+mod tags;
+mod annotations;
+mod reading_order;
+mod furniture;
+mod block_config;
+mod blocks;
+mod outline;
+mod normalize;
+mod tables;
+mod figures;
+mod equations;
+mod footnotes;
 
-use crate::item::ItemType;
+use crate::item::{Author, Date, GlobalId, IdSource, Item, ItemType, Precision};
+use crate::layout::validate_page;
+use crate::preprint::{Preprint, Server};
 
 fn substr(s: &str, from: usize, to: usize) -> String {
     s.chars().skip(from).take(to).collect()
@@ -18,18 +52,18 @@ fn max_font(v: &Vec<poppler::TextAttr>) -> (f64, usize, usize) {
         |acc, x| if x.font_size > acc.0 {(x.font_size, x.start_index, x.end_index)} else {acc})
 }
 
-fn validate_page(s: &str, attr: &Vec<poppler::TextAttr>, layout: &Vec<poppler::ffi::PopplerRectangle>) -> 
-  Result<(),glib::error::Error> {
-    let char_cnt = s.chars().count();
-    let end_attr = attr[attr.len() - 1].end_index;
-    let layout_len = layout.len();
-    if (char_cnt == layout_len) && (char_cnt == end_attr + 1) {
-        Ok(())
-    }
-    else {
-        Err(glib::error::Error::new(glib::FileError::Failed,
-            "XXX-ginger: Page invariants are broken"))
+fn page_text(doc: &poppler::PopplerDocument, num: usize) -> Result<String,glib::error::Error> {
+    let page = doc.get_page(num)?;
+    let text = page.get_text();
+    if text.is_empty() {
+        return Ok(String::new())
     }
+    let layout = page.get_text_layout()?;
+    let attr = page.get_text_attributes();
+
+    validate_page(&text, &attr, &layout)?;
+
+    Ok(String::from(text))
 }
 
 fn named(s: &str) -> bool {
@@ -43,6 +77,48 @@ fn named(s: &str) -> bool {
     v2.len() == 3
 }
 
+// "Last.First,Last.First--Title--Publisher"
+fn named_item(file: &str) -> Item {
+    let path = std::path::Path::new(file);
+    let stem = path.file_stem().unwrap().to_str().unwrap();
+    let mut v = stem.split("--").collect::<Vec<_>>();
+    if v.len() != 3 {
+        v = stem.split("..").collect::<Vec<_>>();
+    }
+    let mut item = Item::default();
+    item.title = String::from(v[1].trim());
+    item.authors = v[0].split(',').map(|a| match a.find('.') {
+        Some(i) => Author {
+            first_name: String::from(a[i+1..].trim()),
+            last_name: String::from(a[..i].trim())
+        },
+        None => Author::from_name(a)
+    }).collect();
+    item
+}
+
+fn name_part(s: &str) -> String {
+    s.replace("--", "-").replace('/', "-").replace(',', " ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// inverse of named_item; None without a title and authors
+fn named_file_name(item: &Item) -> Option<String> {
+    if item.title.is_empty() || item.authors.is_empty() {
+        return None
+    }
+    let authors = item.authors.iter()
+        .map(|a| format!("{}.{}", name_part(&a.last_name).replace('.', ""), name_part(&a.first_name)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let publisher = match (&item.publisher, &item.journal, item.pub_date) {
+        (&Some(ref p), _, _) => name_part(p),
+        (_, &Some(ref j), _) => name_part(&j.journal),
+        (_, _, Some(d)) => d.year.to_string(),
+        _ => String::new()
+    };
+    Some(format!("{}--{}--{}.pdf", authors, name_part(&item.title), publisher))
+}
+
 fn springer(doc: &poppler::PopplerDocument) -> Result<bool,glib::error::Error> {
     if doc.get_n_pages() < 2 {
         return Ok(false)
@@ -63,9 +139,10 @@ fn springer(doc: &poppler::PopplerDocument) -> Result<bool,glib::error::Error> {
 
 }
 
-fn arxiv(doc: &poppler::PopplerDocument) -> Result<bool,glib::error::Error> {
+// the margin stamp "arXiv:1501.00001v2 [cs.LG] 3 Jan 2015" is the largest font of the first page
+fn arxiv_stamp(doc: &poppler::PopplerDocument) -> Result<Option<String>,glib::error::Error> {
     if doc.get_n_pages() < 2 {
-        return Ok(false)
+        return Ok(None)
     }
     let page = doc.get_page(0)?;
     let text = page.get_text();
@@ -78,7 +155,129 @@ fn arxiv(doc: &poppler::PopplerDocument) -> Result<bool,glib::error::Error> {
     //let zz = attr.sort_by(|a,b| a.font_size.partial_cmp(&b.font_size).unwrap());
     let yy = substr(&text, s, e-s+1);
     //println!("{}", yy.trim());
-    Ok(yy.starts_with("arXiv"))
+    if yy.starts_with("arXiv") {
+        Ok(Some(yy))
+    }
+    else {
+        Ok(None)
+    }
+}
+
+fn arxiv(doc: &poppler::PopplerDocument) -> Result<bool,glib::error::Error> {
+    Ok(arxiv_stamp(doc)?.is_some())
+}
+
+fn hal(doc: &poppler::PopplerDocument) -> Result<Option<GlobalId>,glib::error::Error> {
+    if doc.get_n_pages() < 2 {
+        return Ok(None)
+    }
+    let text = page_text(doc, 0)?;
+    if !archive::is_hal_cover(&text) {
+        return Ok(None)
+    }
+    Ok(archive::hal_id(&text))
+}
+
+fn citeseerx(doc: &poppler::PopplerDocument) -> Result<Option<GlobalId>,glib::error::Error> {
+    if doc.get_n_pages() < 1 {
+        return Ok(None)
+    }
+    let text = page_text(doc, 0)?;
+    Ok(archive::citeseerx_id(&text))
+}
+
+fn preprint(doc: &poppler::PopplerDocument) -> Result<Option<Preprint>,glib::error::Error> {
+    if doc.get_n_pages() < 1 {
+        return Ok(None)
+    }
+    let text = page_text(doc, 0)?;
+    let lines = layout::page_lines(doc, 0)?;
+    let footer = furniture::edges(&lines).into_iter().map(|i| lines[i].text.clone()).collect::<Vec<_>>();
+    Ok(preprint::preprint(&text, &footer.join("\n")))
+}
+
+// HAL prepends its own cover page to the paper
+fn title_page(doc: &poppler::PopplerDocument) -> Result<usize,glib::error::Error> {
+    if doc.get_n_pages() > 1 && archive::is_hal_cover(&page_text(doc, 0)?) {
+        Ok(1)
+    }
+    else {
+        Ok(0)
+    }
+}
+
+fn author_line(s: &str) -> Vec<Author> {
+    s.split(|c| c == ',' || c == '&' || c == ';')
+        .flat_map(|x| x.split(" and "))
+        .map(|x| x.trim_matches(|c: char| !c.is_alphabetic()))
+        .filter(|x| !x.is_empty())
+        .map(Author::from_name)
+        .collect()
+}
+
+// `skip_stamp` passes over the arXiv margin stamp, the largest font on an arXiv first page
+fn title_authors(doc: &poppler::PopplerDocument, num: usize, skip_stamp: bool) -> Result<(String, Vec<Author>),glib::error::Error> {
+    let page = doc.get_page(num)?;
+    let text = page.get_text();
+    if text.is_empty() {
+        return Ok((String::new(), vec![]))
+    }
+    let layout = page.get_text_layout()?;
+    let attr = page.get_text_attributes();
+
+    validate_page(&text, &attr, &layout)?;
+
+    let (_, mut s, mut e) = max_font(&attr);
+    if skip_stamp {
+        let mut by_size = attr.iter().collect::<Vec<_>>();
+        by_size.sort_by(|a, b| b.font_size.partial_cmp(&a.font_size).unwrap());
+        let run = by_size.iter()
+            .map(|x| (x.start_index, x.end_index))
+            .find(|&(s, e)| {
+                let t = substr(&text, s, e-s+1);
+                !t.trim_start().starts_with("arXiv") && t.chars().any(|c| c.is_alphabetic())
+            });
+        if let Some(x) = run {
+            s = x.0;
+            e = x.1;
+        }
+    }
+    let title = substr(&text, s, e-s+1);
+    // the author line follows the title; footnote markers on the names are dropped
+    let rest = text.chars().skip(e + 1).collect::<String>();
+    let mut at = e + 1;
+    let mut line = String::new();
+    for l in rest.split('\n') {
+        let n = l.chars().count();
+        if !l.trim().is_empty() {
+            let sizes = (at .. at + n).map(|i| layout::font(&attr, i, i).font_size).collect::<Vec<_>>();
+            line = footnotes::strip_markers(l, &sizes);
+            break
+        }
+        at += n + 1;
+    }
+    Ok((title.split_whitespace().collect::<Vec<_>>().join(" "), author_line(line.trim())))
+}
+
+fn thesis(doc: &poppler::PopplerDocument) -> Result<Option<item::Thesis>,glib::error::Error> {
+    // the title page is sometimes preceded by a blank or cover page
+    let num_pages = std::cmp::min(doc.get_n_pages(), 2);
+    for page_num in 0..num_pages {
+        let page = doc.get_page(page_num)?;
+        let text = page.get_text();
+        if text.is_empty() {
+            continue
+        }
+        let layout = page.get_text_layout()?;
+        let attr = page.get_text_attributes();
+
+        validate_page(&text, &attr, &layout)?;
+
+        if let Some(x) = thesis::thesis(&text) {
+            return Ok(Some(x))
+        }
+    }
+    Ok(None)
 }
 
 fn run(filename: &str) -> Result<(), glib::error::Error> {
@@ -119,52 +318,901 @@ fn run(filename: &str) -> Result<(), glib::error::Error> {
     Ok(())
 }
 
-fn process_file(file: &str) -> Result<ItemType, glib::error::Error> {
-    if named(file) {
-        return Ok(ItemType::Named)
+fn classify(doc: &poppler::PopplerDocument, item: &mut Item) -> Result<ItemType, glib::error::Error> {
+    if let Some(x) = hal(doc)? {
+        let (title, authors) = title_authors(doc, title_page(doc)?, false)?;
+        item.title = title;
+        item.authors = authors;
+        item.add_id(x, IdSource::Stamp);
+        return Ok(ItemType::Hal)
     }
 
-    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    if let Some(x) = citeseerx(doc)? {
+        let (title, authors) = title_authors(doc, title_page(doc)?, false)?;
+        item.title = title;
+        item.authors = authors;
+        item.add_id(x, IdSource::Stamp);
+        return Ok(ItemType::CiteSeerX)
+    }
+
+    if let Some(x) = preprint(doc)? {
+        let (title, authors) = title_authors(doc, title_page(doc)?, false)?;
+        item.title = title;
+        item.authors = authors;
+        item.pub_date = x.date();
+        item.add_id(x.id, IdSource::Stamp);
+        return Ok(match x.server {
+            Server::BioRxiv => ItemType::BioRxiv,
+            Server::MedRxiv => ItemType::MedRxiv,
+            Server::Ssrn => ItemType::Ssrn
+        })
+    }
 
-    if arxiv(&doc)? {
+    if arxiv(doc)? {
         return Ok(ItemType::Arxiv)
     }
 
-    if springer(&doc)? {
+    if springer(doc)? {
         return Ok(ItemType::Springer)
     }
 
+    if let Some(x) = thesis(doc)? {
+        if item.pub_date.is_none() {
+            item.pub_date = x.year.map(Date::year);
+        }
+        item.thesis = Some(x);
+        return Ok(ItemType::Thesis)
+    }
+
     Ok(ItemType::Unknown)
 }
 
-fn main() {
-    let matches = clap::App::new("ginger")
-        .version(crate_version!())
-        .arg(clap::Arg::with_name("recursive")
-            .short("r")
-            .long("recursive")
-            .help("Recursive traversal"))
-        .arg(clap::Arg::with_name("FILE")
-            .help("Name of input file")
-            .required(true)
-            .index(1))
-        .get_matches();
+// ids are a bonus: a page that cannot be read or a file poppler_ext cannot open gives fewer of
+// them, never an error for the item
+fn identifiers(file: &str, doc: &poppler::PopplerDocument, item: &mut Item) {
+    let num_pages = std::cmp::min(doc.get_n_pages(), 2);
+    // the stamp and the running heads and footers name the item itself, the rest of the text
+    // may cite other works
+    let mut stamped = vec![];
+    let mut regions: Vec<(usize, layout::BBox)> = vec![];
+    if let Ok(Some(stamp)) = arxiv_stamp(doc) {
+        if let Ok(chars) = layout::page_chars(doc, 0) {
+            for boxes in layout::locate(&chars, &stamp.replace('\n', "")) {
+                regions.extend(boxes.into_iter().map(|b| (0, b)));
+            }
+        }
+        stamped.push(stamp);
+    }
+    for page_num in 0..num_pages {
+        if let Ok(lines) = layout::page_lines(doc, page_num) {
+            for i in furniture::edges(&lines) {
+                stamped.push(lines[i].text.clone());
+                regions.push((page_num, lines[i].b_box.clone()));
+            }
+        }
+    }
+    for x in ident::from_text(&stamped.join("\n")) {
+        item.add_id(x, IdSource::Stamp);
+    }
+    for page_num in 0..num_pages {
+        if let Ok(text) = page_text(doc, page_num) {
+            for x in ident::from_text(&text).into_iter().chain(preprint::ssrn_ids(&text)) {
+                item.add_id(x, IdSource::Text);
+            }
+        }
+    }
+
+    let named = ident::from_filename(file).into_iter().map(|x| x.normalize()).collect::<Vec<_>>();
+    if let Ok(ext) = poppler_ext::Document::new_from_file(file) {
+        if let Some(xmp) = ext.get_metadata() {
+            for x in ident::from_xmp(&xmp) {
+                item.add_id(x, IdSource::Xmp);
+            }
+        }
+        for page_num in 0..num_pages {
+            for link in ext.get_links(page_num) {
+                let x = match ident::from_uri(&link.uri) {
+                    Some(x) => x.normalize(),
+                    None => continue
+                };
+                // links in the body usually go to cited works
+                let placed = regions.iter().any(|&(p, ref b)| {
+                    p == page_num && b.left < link.area.x2 && link.area.x1 < b.right
+                        && b.top < link.area.y2 && link.area.y1 < b.bottom
+                });
+                let known = item.own_ids().chain(named.iter()).any(|y| y.same_work(&x));
+                item.add_id(x, if placed || known { IdSource::Link } else { IdSource::Text });
+            }
+        }
+    }
+
+    for x in named {
+        item.add_id(x, IdSource::Filename);
+    }
+}
+
+// running heads, footers and the received/accepted block of the first page
+fn citation(doc: &poppler::PopplerDocument, item: &mut Item) -> Result<(), glib::error::Error> {
+    if doc.get_n_pages() < 1 {
+        return Ok(())
+    }
+    let text = page_text(doc, title_page(doc)?)?;
+    item.journal = journal::journal_ref(&text);
+    item.history = journal::history(&text);
+    let year = item.journal.as_ref().and_then(|x| x.year).map(Date::year);
+    // a bare year in the history says no more than the journal line
+    let published = item.history.published.filter(|d| year.is_none() || d.precision() > Precision::Year);
+    item.pub_date = published.or(year).or(item.pub_date);
+    Ok(())
+}
+
+// the identifier that names the copy we hold
+fn primary_kind(t: &ItemType, id: &GlobalId) -> bool {
+    match (t, id) {
+        (&ItemType::Arxiv, &GlobalId::ARXIV(_)) => true,
+        (&ItemType::Springer, &GlobalId::DOI(_)) => true,
+        (&ItemType::Hal, &GlobalId::HAL(_)) => true,
+        (&ItemType::CiteSeerX, &GlobalId::CITEER(_)) => true,
+        (&ItemType::BioRxiv, &GlobalId::DOI(_)) => true,
+        (&ItemType::MedRxiv, &GlobalId::DOI(_)) => true,
+        (&ItemType::Ssrn, &GlobalId::SSRN(_)) => true,
+        _ => false
+    }
+}
+
+fn process_file(file: &str) -> Result<(ItemType, Item), glib::error::Error> {
+    let mut item = Item::default();
+
+    if named(file) {
+        return Ok((ItemType::Named, item))
+    }
+
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+
+    let t = classify(&doc, &mut item)?;
+    identifiers(file, &doc, &mut item);
+    citation(&doc, &mut item)?;
+    item.set_primary(|x| primary_kind(&t, x));
 
-    let file = matches.value_of("FILE").unwrap();
+    Ok((t, item))
+}
+
+// process_file, with title and authors for every type: from the file name of named files and
+// from the first page of the others; the library commands match and file items by them
+fn library_item(file: &str) -> Result<(ItemType, Item), glib::error::Error> {
+    let (t, mut item) = process_file(file)?;
+    if t == ItemType::Named {
+        item = named_item(file);
+    }
+    else if item.title.is_empty() {
+        let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+        let (title, authors) = title_authors(&doc, title_page(&doc)?, true)?;
+        item.title = title;
+        item.authors = authors;
+    }
+    Ok((t, item))
+}
+
+fn pdf_files(dir: &str) -> Vec<String> {
+    let mut r = vec![];
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = match entry {
+            Ok(x) => x,
+            Err(_) => continue
+        };
+        if !entry.file_type().is_file() {
+            continue
+        }
+        let is_pdf = entry.path().extension()
+            .and_then(|x| x.to_str())
+            .map_or(false, |x| x.eq_ignore_ascii_case("pdf"));
+        if is_pdf {
+            if let Some(x) = entry.path().to_str() {
+                r.push(String::from(x));
+            }
+        }
+    }
+    r
+}
 
-    if matches.is_present("recursive") {
+fn file_references(file: &str) -> Result<Vec<references::Citation>, glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let pages = furniture::strip(&layout::document_lines(&doc)?);
+    Ok(references::references(&pages))
+}
+
+fn refs(file: &str) -> Result<(), glib::error::Error> {
+    for c in file_references(file)? {
+        println!("{:?}", c);
+    }
+    Ok(())
+}
+
+fn citation_graph(dir: &str, format: &str) {
+    let mut nodes = vec![];
+    let mut refs = vec![];
+    for file in pdf_files(dir) {
+        match (library_item(&file), file_references(&file)) {
+            // another copy of a work already in the graph is left out
+            (Ok((_, ref item)), _) if nodes.iter().any(|n: &graph::Node| n.item.same_work(item)) => (),
+            (Ok((_, item)), Ok(cs)) => {
+                nodes.push(graph::Node{file, item});
+                refs.push(cs);
+            },
+            _ => println!("error: {}", file)
+        }
+    }
+    let g = graph::build(nodes, &refs);
+    match format {
+        "graphml" => print!("{}", graph::to_graphml(&g)),
+        "cited-by" => print!("{}", graph::cited_by(&g)),
+        _ => print!("{}", graph::to_dot(&g))
+    }
+}
+
+fn library_files(path: &str) -> Vec<String> {
+    match std::fs::metadata(path) {
+        Ok(ref m) if m.is_dir() => pdf_files(path),
+        _ => vec![String::from(path)]
+    }
+}
+
+fn enrich(path: &str, dump: Option<&str>, crossref: Option<&str>) {
+    let index = match dump.map(oai::ArxivIndex::load) {
+        Some(Ok(x)) => {
+            println!("{} records in {}", x.len(), dump.unwrap());
+            Some(x)
+        },
+        Some(Err(e)) => {
+            println!("ERROR: {}: {}", dump.unwrap(), e);
+            return
+        },
+        None => None
+    };
+    let source = match crossref.map(crossref::Source::open) {
+        Some(Ok(x)) => Some(x),
+        Some(Err(e)) => {
+            println!("ERROR: {}: {}", crossref.unwrap(), e);
+            return
+        },
+        None => None
+    };
+    for file in library_files(path) {
+        let mut item = match library_item(&file) {
+            Ok((_, x)) => x,
+            Err(_) => {
+                println!("error: {}", file);
+                continue
+            }
+        };
+        if let Some(ref index) = index {
+            match oai::enrich(&mut item, index) {
+                Some(m) => {
+                    if m.outdated() {
+                        println!("outdated: {} (arXiv:{} local v{}, latest v{})",
+                            file, m.id, m.local_version.unwrap_or(0), m.latest_version);
+                    }
+                    else {
+                        println!("arxiv: {}", file);
+                    }
+                },
+                None => println!("not in arXiv dump: {}", file)
+            }
+        }
+        if let Some(ref source) = source {
+            let doi = crossref::item_doi(&item);
+            match doi.as_ref().map(|x| source.lookup(x)) {
+                Some(Ok(Some(work))) => {
+                    println!("crossref: {}", file);
+                    crossref::reconcile(&mut item, &work);
+                    for d in item.disagreements.iter().filter(|x| x.source == "crossref") {
+                        println!("  disagree {}: extracted {:?}, crossref {:?}", d.field, d.extracted, d.value);
+                    }
+                },
+                Some(Ok(None)) => println!("not in crossref: {}", file),
+                Some(Err(e)) => println!("ERROR: {}: {}", file, e),
+                None => ()
+            }
+        }
+        println!("{:?}", item);
+    }
+}
+
+fn reconcile(bib: &str, dir: &str, write_bib: Option<&str>) {
+    let src = match std::fs::read_to_string(bib) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERROR: {}: {}", bib, e);
+            return
+        }
+    };
+    let entries = bibtex::parse(&src);
+    let mut files: Vec<(usize, String)> = vec![];
+    for file in pdf_files(dir) {
+        let item = match library_item(&file) {
+            Ok((_, x)) => x,
+            Err(_) => {
+                println!("error: {}", file);
+                continue
+            }
+        };
+        match bibtex::match_entry(&entries, &item) {
+            Some((i, via)) => {
+                let via = if via == bibtex::Via::Id { "id" } else { "title" };
+                println!("matched: {} <- {} ({})", entries[i].key, file, via);
+                for c in bibtex::conflicts(&entries[i], &item) {
+                    println!("  conflict {}: pdf {:?}, bib {:?}", c.field, c.pdf, c.bib);
+                }
+                files.push((i, file));
+            },
+            None => println!("no entry: {}", file)
+        }
+    }
+    for (i, e) in entries.iter().enumerate() {
+        if !files.iter().any(|x| x.0 == i) {
+            println!("no pdf: {}", e.key);
+        }
+    }
+    if let Some(out) = write_bib {
+        if let Err(e) = std::fs::write(out, bibtex::with_files(&src, &entries, &files)) {
+            println!("ERROR: {}: {}", out, e);
+        }
+    }
+}
+
+fn document_text(file: &str) -> Result<Vec<String>, glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let mut r = vec![];
+    for i in 0 .. doc.get_n_pages() {
+        r.push(String::from(doc.get_page(i)?.get_text()));
+    }
+    let mut norm = normalize::Normalizer::new();
+    for p in &r {
+        norm.learn(p);
+    }
+    Ok(r.iter().map(|p| norm.text(p)).collect())
+}
+
+// --raw and --words of the text commands
+fn text_normalizer(m: &clap::ArgMatches, pages: &[Vec<layout::TT>]) -> normalize::Normalizer {
+    let mut norm = normalize::Normalizer::new();
+    norm.raw = m.is_present("raw");
+    if let Some(path) = m.value_of("words") {
+        if let Err(e) = norm.load_words(path) {
+            println!("error: {}: {}", path, e);
+        }
+    }
+    for p in pages {
+        for t in p {
+            norm.learn(&t.text);
+        }
+    }
+    norm
+}
+
+fn index_doc(file: &str, hash: &str) -> Result<search::Doc, glib::error::Error> {
+    let (_, item) = library_item(file)?;
+    Ok(search::Doc::new(file, hash, &item, document_text(file)?))
+}
+
+fn open_index(path: &str) -> search::Index {
+    match search::Index::load(path) {
+        Ok(x) => x,
+        Err(_) => search::Index::new()
+    }
+}
+
+fn build_index(dir: &str, path: &str) {
+    let mut index = open_index(path);
+    for file in pdf_files(dir) {
+        let hash = match tags::content_hash(&file) {
+            Ok(x) => x,
+            Err(_) => {
+                println!("error: {}", file);
+                continue
+            }
+        };
+        // files indexed with the same content are not read again
+        if index.unchanged(&file, &hash) {
+            continue
+        }
+        match index_doc(&file, &hash) {
+            Ok(d) => index.add(d),
+            Err(_) => {
+                // the old text is no longer the file's
+                index.retain(|d| d.file != file);
+                println!("error: {}", file)
+            }
+        }
+    }
+    index.retain(|d| std::path::Path::new(&d.file).exists());
+    if let Err(e) = index.save(path) {
+        println!("ERROR: {}: {}", path, e);
+    }
+}
+
+fn search_index(query: &str, path: &str) {
+    let index = match search::Index::load(path) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERROR: {}: {}", path, e);
+            return
+        }
+    };
+    for h in search::search(&index, &search::parse_query(query)) {
+        let pages = h.pages.iter().map(|x| (x + 1).to_string()).collect::<Vec<_>>().join(", ");
+        println!("{:.3} {} (pages {})", h.score, index.docs[h.doc].file, pages);
+        if !h.snippet.is_empty() {
+            println!("  {}", h.snippet);
+        }
+    }
+}
+
+fn watch_inbox(dir: &str, index_path: &str, library: Option<&str>, settle: u64) {
+    let mut inbox = match watch::Inbox::new(dir, std::time::Duration::from_secs(settle)) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERROR: {}: {}", dir, e);
+            return
+        }
+    };
+    println!("watching: {}", dir);
+    loop {
+        let ready = match inbox.next() {
+            Ok(x) => x,
+            Err(e) => {
+                println!("ERROR: {}: {}", dir, e);
+                return
+            }
+        };
+        let mut index = open_index(index_path);
+        for path in ready {
+            let mut file = match path.to_str() {
+                Some(x) => String::from(x),
+                None => continue
+            };
+            let (t, item) = match library_item(&file) {
+                Ok(x) => x,
+                Err(_) => {
+                    println!("error: {}", file);
+                    continue
+                }
+            };
+            println!("classified {}: {}", item::type_name(&t), file);
+            if let Some(lib) = library {
+                let name = named_file_name(&item)
+                    .or_else(|| path.file_name().and_then(|x| x.to_str()).map(String::from))
+                    .unwrap();
+                let dest = std::path::Path::new(lib).join(name);
+                if dest.exists() {
+                    println!("exists, not moved: {} -> {}", file, dest.display());
+                }
+                else {
+                    match organize::move_file(&path, &dest) {
+                        Ok(()) => {
+                            println!("moved: {} -> {}", file, dest.display());
+                            file = dest.to_string_lossy().into_owned();
+                        },
+                        Err(e) => println!("ERROR: {}: {}", file, e)
+                    }
+                }
+            }
+            match (document_text(&file), tags::content_hash(&file)) {
+                (Ok(pages), Ok(hash)) => {
+                    index.add(search::Doc::new(&file, &hash, &item, pages));
+                    println!("indexed: {}", file);
+                },
+                _ => println!("error: {}", file)
+            }
+        }
+        if let Err(e) = index.save(index_path) {
+            println!("ERROR: {}: {}", index_path, e);
+        }
+    }
+}
+
+fn organize_library(src: &str, dest: &str, layout: &str, mode: organize::Mode,
+                    policy: organize::Conflict, dry_run: bool, journal: &str) {
+    let mut taken = vec![];
+    let mut plan = vec![];
+    for file in pdf_files(src) {
+        match library_item(&file) {
+            Ok((t, item)) => {
+                let dir = organize::expand(layout, &t, &item);
+                let path = std::path::Path::new(&file);
+                match organize::plan(path, std::path::Path::new(dest), &dir, policy, &taken) {
+                    Some(a) => {
+                        taken.push(a.to.clone());
+                        taken.extend(a.backup.clone());
+                        plan.push(a);
+                    },
+                    None => println!("skip: {}", file)
+                }
+            },
+            Err(_) => println!("error: {}", file)
+        }
+    }
+    if dry_run {
+        for a in &plan {
+            match a.backup {
+                Some(_) => println!("{:?}: {} -> {} (overwrite)", mode, a.from.display(), a.to.display()),
+                None => println!("{:?}: {} -> {}", mode, a.from.display(), a.to.display())
+            }
+        }
+        return
+    }
+    let mut j = match organize::open_journal(std::path::Path::new(journal)) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERROR: {}: {}", journal, e);
+            return
+        }
+    };
+    for a in &plan {
+        match organize::apply(a, mode, &mut j) {
+            Ok(()) => println!("{:?}: {} -> {}", mode, a.from.display(), a.to.display()),
+            Err(e) => println!("ERROR: {}: {}", a.from.display(), e)
+        }
+    }
+}
+
+fn undo_organize(journal: &str) {
+    match organize::undo(std::path::Path::new(journal)) {
+        Ok(failed) => {
+            for x in failed {
+                println!("error: {}", x);
+            }
+        },
+        Err(e) => println!("ERROR: {}: {}", journal, e)
+    }
+}
+
+fn tag_files(m: &clap::ArgMatches, index_path: &str) {
+    let mut index = open_index(index_path);
+    let (cmd, m) = match m.subcommand() {
+        (c, Some(x)) => (c, x),
+        _ => return
+    };
+    if cmd == "list" && !m.is_present("FILE") {
+        let mut all: Vec<(&String, Vec<&str>)> = vec![];
+        for d in &index.docs {
+            if let Some(n) = index.doc_notes(d) {
+                for t in &n.tags {
+                    match all.iter_mut().find(|x| x.0 == t) {
+                        Some(x) => x.1.push(&d.file),
+                        None => all.push((t, vec![&d.file]))
+                    }
+                }
+            }
+        }
+        all.sort();
+        for (t, files) in all {
+            println!("{} ({})", t, files.len());
+            for f in files {
+                println!("  {}", f);
+            }
+        }
+        return
+    }
+    let file = m.value_of("FILE").unwrap();
+    let rating = match m.value_of("rating").map(|r| r.parse::<u8>()) {
+        Some(Ok(x)) if x <= 5 => Some(x),
+        Some(_) => {
+            println!("ERROR: rating must be 0 to 5: {}", m.value_of("rating").unwrap());
+            return
+        },
+        None => None
+    };
+    let hash = match tags::content_hash(file) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERROR: {}: {}", file, e);
+            return
+        }
+    };
+    {
+        let n = index.notes.entry(hash.clone()).or_insert_with(tags::Notes::default);
+        for t in m.values_of("TAG").into_iter().flat_map(|x| x) {
+            if cmd == "add" { n.add_tag(t) } else { n.remove_tag(t) }
+        }
+        if cmd == "add" {
+            if rating.is_some() {
+                n.rating = rating;
+            }
+            if let Some(x) = m.value_of("note") {
+                n.note = Some(String::from(x));
+            }
+        }
+        println!("{}: tags [{}] rating {} note {:?}", file, n.tags.join(", "),
+            n.rating.map_or(String::from("-"), |x| x.to_string()), n.note.as_ref().map_or("", |x| x.as_str()));
+    }
+    if cmd != "list" {
+        if let Err(e) = index.save(index_path) {
+            println!("ERROR: {}: {}", index_path, e);
+        }
+    }
+}
+
+// indexed items as JSON lines, with their tags
+fn export(query: &str, index_path: &str) {
+    let index = match search::Index::load(index_path) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERROR: {}: {}", index_path, e);
+            return
+        }
+    };
+    let q = search::parse_query(query);
+    for d in index.docs.iter().filter(|d| search::filter(&index, d, &q)) {
+        let notes = index.doc_notes(d).cloned().unwrap_or_default();
+        println!("{}", serde_json::json!({
+            "file": d.file,
+            "title": d.title,
+            "authors": d.authors,
+            "year": d.year,
+            "ids": d.ids.iter().map(search::DocId::to_json).collect::<Vec<_>>(),
+            "sha256": d.hash,
+            "tags": notes.tags,
+            "rating": notes.rating,
+            "note": notes.note,
+        }));
+    }
+}
+
+fn list_annotations(path: &str, format: &str) {
+    for file in library_files(path) {
+        match annotations::annotations(&file) {
+            Ok(ref a) if a.is_empty() => (),
+            Ok(a) => {
+                if format == "json" {
+                    println!("{}", annotations::to_json(&file, &a));
+                }
+                else {
+                    print!("{}", annotations::to_markdown(&file, &a));
+                }
+            },
+            Err(e) => println!("ERROR: {}: {}", file, e)
+        }
+    }
+}
+
+// display equation flags of the page lines
+fn display_lines(doc: &poppler::PopplerDocument, pages: &[Vec<layout::TT>]) -> Result<Vec<Vec<bool>>, glib::error::Error> {
+    let mut r = vec![];
+    for (i, lines) in pages.iter().enumerate() {
+        let math = blocks::math_shares(&layout::page_chars(doc, i)?, lines.len());
+        r.push(blocks::display_lines(lines, &math));
+    }
+    Ok(r)
+}
+
+fn body_size(pages: &[Vec<layout::TT>]) -> f64 {
+    match outline::font_histogram(pages).first() {
+        Some(x) => (x.0).1 as f64 / 10.0,
+        None => 0.0
+    }
+}
+
+// footnotes of every page with their superscript refs; `pages` without furniture
+fn page_footnotes(doc: &poppler::PopplerDocument, pages: &[Vec<layout::TT>]) -> Result<Vec<Vec<footnotes::Footnote>>, glib::error::Error> {
+    let size = body_size(pages);
+    let mut r = vec![];
+    for (i, lines) in pages.iter().enumerate() {
+        let mut notes = footnotes::page_footnotes(i, lines, size);
+        if !notes.is_empty() {
+            footnotes::link(&mut notes, i, &footnotes::superscripts(&layout::page_chars(doc, i)?));
+        }
+        r.push(notes);
+    }
+    Ok(r)
+}
+
+fn list_footnotes(file: &str) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let pages = furniture::strip(&layout::document_lines(&doc)?);
+    let bbox = |b: &layout::BBox| serde_json::json!({"left": b.left, "top": b.top, "right": b.right, "bottom": b.bottom});
+    let mut r = vec![];
+    for f in page_footnotes(&doc, &pages)?.iter().flat_map(|x| x.iter()) {
+        r.push(serde_json::json!({
+            "page": f.page + 1,
+            "marker": f.marker,
+            "text": f.text,
+            "bbox": bbox(&f.b_box),
+            "refs": f.refs.iter().map(|x| serde_json::json!({"page": x.page + 1, "bbox": bbox(&x.b_box)})).collect::<Vec<_>>(),
+        }));
+    }
+    println!("{}", serde_json::to_string_pretty(&serde_json::Value::Array(r)).unwrap());
+    Ok(())
+}
+
+fn page_texts(file: &str, all: bool, m: &clap::ArgMatches) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let mut pages = layout::document_lines(&doc)?;
+    let mut display = display_lines(&doc, &pages)?;
+    if !all {
+        let roles = furniture::roles(&pages);
+        pages = furniture::keep_body(&roles, &pages);
+        display = furniture::keep_body(&roles, &display);
+    }
+    let notes = page_footnotes(&doc, &pages)?;
+    let norm = text_normalizer(m, &pages);
+    for (i, lines) in pages.iter().enumerate() {
+        if i > 0 {
+            println!("\u{c}");
+        }
+        // footnotes go after the text of the page
+        let order = reading_order::reading_order(lines).into_iter()
+            .filter(|j| !notes[i].iter().any(|f| f.lines.contains(j)))
+            .collect::<Vec<_>>();
+        let text = order.iter().map(|&j| lines[j].text.as_str()).collect::<Vec<_>>().join("\n");
+        // display equations are marked
+        for (&j, t) in order.iter().zip(norm.text(&text).split('\n')) {
+            if display[i][j] {
+                println!("$$ {} $$", t);
+            }
+            else {
+                println!("{}", t);
+            }
+        }
+        for f in &notes[i] {
+            println!("[^{}]: {}", f.marker, norm.text(&f.text).replace('\n', " "));
+        }
+    }
+    Ok(())
+}
+
+fn page_words(file: &str, page: Option<usize>) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let pages = match page {
+        Some(n) if n >= 1 && n <= doc.get_n_pages() => vec![n - 1],
+        Some(_) => return Err(glib::error::Error::new(glib::FileError::Failed, "XXX-ginger: No such page")),
+        None => (0 .. doc.get_n_pages()).collect()
+    };
+    for i in pages {
+        for w in layout::page_words(&doc, i)? {
+            println!("{}\t{:.1}\t{:.1}\t{:.1}\t{:.1}\t{:.1}\t{}\t{:.1}\t{}", i + 1,
+                w.b_box.left, w.b_box.top, w.b_box.right, w.b_box.bottom, w.baseline, w.font_name, w.font_size, w.text);
+        }
+    }
+    Ok(())
+}
+
+fn block_config(m: &clap::ArgMatches) -> blocks::Config {
+    blocks::Config {
+        gap_factor: value_t!(m, "gap-factor", f64).unwrap_or_else(|e| e.exit()),
+        max_gap: value_t!(m, "max-gap", f64).unwrap_or_else(|e| e.exit()),
+        spacing_tolerance: value_t!(m, "spacing-tolerance", f64).unwrap_or_else(|e| e.exit()),
+        indent: value_t!(m, "indent", f64).unwrap_or_else(|e| e.exit()),
+        split_on_font: !m.is_present("keep-fonts"),
+    }
+}
+
+fn page_blocks(file: &str, cfg: &blocks::Config, m: &clap::ArgMatches) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let all = layout::document_lines(&doc)?;
+    let roles = furniture::roles(&all);
+    let mut pages = furniture::keep_body(&roles, &all);
+    let mut display = furniture::keep_body(&roles, &display_lines(&doc, &all)?);
+    let notes = page_footnotes(&doc, &pages)?;
+    // footnotes are blocks of their own
+    for (i, ns) in notes.iter().enumerate() {
+        let keep = (0 .. pages[i].len()).map(|j| !ns.iter().any(|f| f.lines.contains(&j))).collect::<Vec<_>>();
+        pages[i] = pages[i].iter().zip(keep.iter()).filter(|x| *x.1).map(|x| x.0.clone()).collect();
+        display[i] = display[i].iter().zip(keep.iter()).filter(|x| *x.1).map(|x| *x.0).collect();
+    }
+    let norm = text_normalizer(m, &pages);
+    for (i, bs) in blocks::document_blocks(&pages, &display, cfg).iter().enumerate() {
+        for b in bs {
+            println!("=== page {} {} {}{}", i + 1, b.font_name, b.font_size, if b.equation { " equation" } else { "" });
+            println!("{}", norm.text(&b.text));
+        }
+        for f in &notes[i] {
+            println!("=== page {} footnote {}", i + 1, f.marker);
+            println!("{}", norm.text(&f.text));
+        }
+    }
+    Ok(())
+}
+
+fn document_outline(file: &str) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let pages = furniture::strip(&layout::document_lines(&doc)?);
+    let bookmarks = poppler_ext::Document::new_from_file(file)?.get_outline();
+    let sections = outline::tree(outline::merge(outline::headings(&pages), &bookmarks));
+    println!("{}", serde_json::to_string_pretty(&outline::to_json(file, &sections)).unwrap());
+    Ok(())
+}
+
+fn document_tables(file: &str, out: Option<&str>) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let stem = std::path::Path::new(file).file_stem().unwrap().to_str().unwrap();
+    let mut n = 0;
+    for i in 0 .. doc.get_n_pages() {
+        let lines = layout::page_lines(&doc, i)?;
+        for t in tables::page_tables(i, &lines, &layout::page_words(&doc, i)?) {
+            n += 1;
+            match out {
+                Some(dir) => {
+                    let path = std::path::Path::new(dir).join(format!("{}-table{}.csv", stem, n));
+                    if let Err(e) = std::fs::write(&path, tables::to_csv(&t)) {
+                        println!("error: {}: {}", path.display(), e);
+                    }
+                },
+                None => {
+                    println!("# page {}: {}", i + 1, t.caption.as_ref().map_or("", |x| x.as_str()));
+                    println!("{}", tables::to_csv(&t));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn document_figures(file: &str, png: Option<&str>, scale: f64) -> Result<(), glib::error::Error> {
+    let doc = poppler::PopplerDocument::new_from_file(file, "")?;
+    let pages = furniture::strip(&layout::document_lines(&doc)?);
+    let size = body_size(&pages);
+    let ext = match png {
+        Some(_) => Some(poppler_ext::Document::new_from_file(file)?),
+        None => None
+    };
+    let stem = std::path::Path::new(file).file_stem().unwrap().to_str().unwrap();
+    let mut r = vec![];
+    for (i, lines) in pages.iter().enumerate() {
+        let (_, height) = doc.get_page(i)?.get_size();
+        for f in figures::page_figures(i, lines, size, height) {
+            let b = &f.b_box;
+            let mut v = serde_json::json!({
+                "page": i + 1,
+                "bbox": {"left": b.left, "top": b.top, "right": b.right, "bottom": b.bottom},
+                "number": f.number,
+                "caption": f.caption,
+            });
+            if let (Some(dir), Some(d)) = (png, &ext) {
+                let path = std::path::Path::new(dir).join(format!("{}-figure{}.png", stem, r.len() + 1));
+                let path = path.to_str().unwrap();
+                let area = poppler_ext::Rect{x1: b.left, y1: b.top, x2: b.right, y2: b.bottom};
+                match d.render_png(i, &area, scale, path) {
+                    Ok(()) => v["png"] = serde_json::json!(path),
+                    Err(e) => println!("error: {}: {}", path, e)
+                }
+            }
+            r.push(v);
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&serde_json::Value::Array(r)).unwrap());
+    Ok(())
+}
+
+fn classify_files(file: &str, recursive: bool) {
+    if recursive {
+        // files seen so far, to report copies of the same work
+        let mut seen: Vec<(String, Item)> = vec![];
         for entry in walkdir::WalkDir::new(file) {
             let entry = entry.unwrap();
             let path = entry.path();
             let meta = std::fs::metadata(path).unwrap();
             if meta.file_type().is_file() {
                 let file = entry.path().to_str().unwrap();
-                match process_file(file) {
+                let r = process_file(file);
+                match r {
                     Err(_) => println!("error: {}", file),
-                    Ok(ItemType::Unknown) => println!("unknown: {}", file),
-                    Ok(ItemType::Arxiv) => println!("arxiv: {}", file),
-                    Ok(ItemType::Springer) => println!("springer: {}", file),
-                    Ok(ItemType::Named) => println!("named: {}", file)
+                    Ok((ItemType::Unknown, _)) => println!("unknown: {}", file),
+                    Ok((ItemType::Arxiv, _)) => println!("arxiv: {}", file),
+                    Ok((ItemType::Springer, _)) => println!("springer: {}", file),
+                    Ok((ItemType::Named, _)) => println!("named: {}", file),
+                    Ok((ItemType::Thesis, _)) => println!("thesis: {}", file),
+                    Ok((ItemType::Hal, _)) => println!("hal: {}", file),
+                    Ok((ItemType::CiteSeerX, _)) => println!("citeseerx: {}", file),
+                    Ok((ItemType::BioRxiv, _)) => println!("biorxiv: {}", file),
+                    Ok((ItemType::MedRxiv, _)) => println!("medrxiv: {}", file),
+                    Ok((ItemType::Ssrn, _)) => println!("ssrn: {}", file)
+                }
+                if let Ok((_, item)) = r {
+                    if let Some(&(ref other, _)) = seen.iter().find(|x| x.1.same_work(&item)) {
+                        println!("duplicate: {} {}", file, other);
+                    }
+                    seen.push((String::from(file), item));
                 }
             }
         }
@@ -172,6 +1220,397 @@ fn main() {
     else {
         println!("{:?}", process_file(file));
     }
+}
+
+fn main() {
+    let matches = clap::App::new("ginger")
+        .version(crate_version!())
+        .setting(clap::AppSettings::SubcommandsNegateReqs)
+        .arg(clap::Arg::with_name("recursive")
+            .short("r")
+            .long("recursive")
+            .help("Recursive traversal"))
+        .arg(clap::Arg::with_name("FILE")
+            .help("Name of input file")
+            .required(true)
+            .index(1))
+        .subcommand(clap::SubCommand::with_name("refs")
+            .about("Extract the reference list")
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("graph")
+            .about("Citation graph of the PDFs in a directory")
+            .arg(clap::Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["dot", "graphml", "cited-by"])
+                .default_value("dot")
+                .help("Output format"))
+            .arg(clap::Arg::with_name("DIR")
+                .help("Library directory")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("enrich")
+            .about("Fill in metadata from local arXiv and Crossref snapshots")
+            .arg(clap::Arg::with_name("arxiv-dump")
+                .long("arxiv-dump")
+                .takes_value(true)
+                .required_unless("crossref")
+                .help("arXivRaw or oai_dc XML file, or a directory of them"))
+            .arg(clap::Arg::with_name("crossref")
+                .long("crossref")
+                .takes_value(true)
+                .help("Crossref JSON-lines snapshot, or http://HOST:PORT serving /works/{doi}"))
+            .arg(clap::Arg::with_name("PATH")
+                .help("PDF file or library directory")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("reconcile")
+            .about("Match a .bib file against the PDFs in a directory")
+            .arg(clap::Arg::with_name("write-bib")
+                .long("write-bib")
+                .takes_value(true)
+                .help("Write a copy of the .bib with file fields for matched entries"))
+            .arg(clap::Arg::with_name("BIB")
+                .help("BibTeX file")
+                .required(true)
+                .index(1))
+            .arg(clap::Arg::with_name("DIR")
+                .help("Library directory")
+                .required(true)
+                .index(2)))
+        .subcommand(clap::SubCommand::with_name("index")
+            .about("Index the text of the PDFs in a directory")
+            .arg(clap::Arg::with_name("index")
+                .long("index")
+                .takes_value(true)
+                .default_value("ginger-index.json")
+                .help("Index file"))
+            .arg(clap::Arg::with_name("DIR")
+                .help("Library directory")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("search")
+            .about("Search the index: words, \"phrases\", author:NAME, year:YYYY[-YYYY]")
+            .arg(clap::Arg::with_name("index")
+                .long("index")
+                .takes_value(true)
+                .default_value("ginger-index.json")
+                .help("Index file"))
+            .arg(clap::Arg::with_name("QUERY")
+                .help("Query")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("watch")
+            .about("Process PDFs as they arrive in an inbox directory")
+            .arg(clap::Arg::with_name("index")
+                .long("index")
+                .takes_value(true)
+                .default_value("ginger-index.json")
+                .help("Index file"))
+            .arg(clap::Arg::with_name("library")
+                .long("library")
+                .takes_value(true)
+                .help("Move processed files here under their named form"))
+            .arg(clap::Arg::with_name("settle")
+                .long("settle")
+                .takes_value(true)
+                .default_value("5")
+                .help("Seconds without writes before a file is processed"))
+            .arg(clap::Arg::with_name("DIR")
+                .help("Inbox directory")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("organize")
+            .about("Arrange PDFs into a directory tree by metadata")
+            .arg(clap::Arg::with_name("layout")
+                .long("layout")
+                .takes_value(true)
+                .default_value("{type}/{year}/{first_author}")
+                .help("Directory template: {type} {year} {first_author} {authors} {title} {journal} {publisher}"))
+            .arg(clap::Arg::with_name("mode")
+                .long("mode")
+                .takes_value(true)
+                .possible_values(&["move", "copy", "hardlink", "symlink"])
+                .default_value("move")
+                .help("How files are placed"))
+            .arg(clap::Arg::with_name("on-conflict")
+                .long("on-conflict")
+                .takes_value(true)
+                .possible_values(&["skip", "rename", "overwrite"])
+                .default_value("skip")
+                .help("What to do when the target exists"))
+            .arg(clap::Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .help("Print the plan only"))
+            .arg(clap::Arg::with_name("journal")
+                .long("journal")
+                .takes_value(true)
+                .default_value("ginger-journal.jsonl")
+                .help("Journal of changes, for --undo"))
+            .arg(clap::Arg::with_name("undo")
+                .long("undo")
+                .help("Revert the changes recorded in the journal"))
+            .arg(clap::Arg::with_name("SRC")
+                .help("Source directory")
+                .required_unless("undo")
+                .index(1))
+            .arg(clap::Arg::with_name("DEST")
+                .help("Library root")
+                .required_unless("undo")
+                .index(2)))
+        .subcommand(clap::SubCommand::with_name("tag")
+            .about("Tags, rating and notes, kept in the index by content hash")
+            .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+            .arg(clap::Arg::with_name("index")
+                .long("index")
+                .takes_value(true)
+                .default_value("ginger-index.json")
+                .help("Index file"))
+            .subcommand(clap::SubCommand::with_name("add")
+                .arg(clap::Arg::with_name("rating")
+                    .long("rating")
+                    .takes_value(true)
+                    .help("0 to 5"))
+                .arg(clap::Arg::with_name("note")
+                    .long("note")
+                    .takes_value(true)
+                    .help("Free-text note"))
+                .arg(clap::Arg::with_name("FILE").required(true).index(1))
+                .arg(clap::Arg::with_name("TAG").multiple(true).index(2)))
+            .subcommand(clap::SubCommand::with_name("remove")
+                .arg(clap::Arg::with_name("FILE").required(true).index(1))
+                .arg(clap::Arg::with_name("TAG").multiple(true).required(true).index(2)))
+            .subcommand(clap::SubCommand::with_name("list")
+                .arg(clap::Arg::with_name("FILE").index(1))))
+        .subcommand(clap::SubCommand::with_name("export")
+            .about("Indexed items as JSON lines, filtered like search (tag:, author:, year:)")
+            .arg(clap::Arg::with_name("index")
+                .long("index")
+                .takes_value(true)
+                .default_value("ginger-index.json")
+                .help("Index file"))
+            .arg(clap::Arg::with_name("FILTER")
+                .help("Filters")
+                .default_value("")
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("annots")
+            .about("Highlights and notes of a PDF or of every PDF in a directory")
+            .arg(clap::Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["markdown", "json"])
+                .default_value("markdown")
+                .help("Output format"))
+            .arg(clap::Arg::with_name("PATH")
+                .help("PDF file or library directory")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("text")
+            .about("Page text in reading order, columns left to right")
+            .arg(clap::Arg::with_name("all")
+                .short("a")
+                .long("all")
+                .help("Keep running heads, footers and page numbers"))
+            .arg(clap::Arg::with_name("raw")
+                .long("raw")
+                .help("Text as extracted: no ligature, hyphenation or Unicode clean-up"))
+            .arg(clap::Arg::with_name("words")
+                .long("words")
+                .takes_value(true)
+                .help("Word list deciding which line-end hyphens are kept"))
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("blocks")
+            .about("Paragraphs and other text blocks")
+            .arg(clap::Arg::with_name("gap-factor")
+                .long("gap-factor")
+                .takes_value(true)
+                .default_value("0.4")
+                .help("Split on gaps above this share of the line height when no spacing was learned"))
+            .arg(clap::Arg::with_name("max-gap")
+                .long("max-gap")
+                .takes_value(true)
+                .default_value("1.0")
+                .help("Always split on gaps above this many font sizes"))
+            .arg(clap::Arg::with_name("spacing-tolerance")
+                .long("spacing-tolerance")
+                .takes_value(true)
+                .default_value("0.3")
+                .help("Allowed excess over the learned line spacing, in font sizes"))
+            .arg(clap::Arg::with_name("indent")
+                .long("indent")
+                .takes_value(true)
+                .default_value("0.8")
+                .help("Indentation starting a paragraph, in font sizes"))
+            .arg(clap::Arg::with_name("raw")
+                .long("raw")
+                .help("Text as extracted: no ligature, hyphenation or Unicode clean-up"))
+            .arg(clap::Arg::with_name("words")
+                .long("words")
+                .takes_value(true)
+                .help("Word list deciding which line-end hyphens are kept"))
+            .arg(clap::Arg::with_name("keep-fonts")
+                .long("keep-fonts")
+                .help("Do not split on font changes"))
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("outline")
+            .about("Section tree from headings and the PDF bookmarks, as JSON")
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("words")
+            .about("Words with their boxes, baseline and font, tab separated")
+            .arg(clap::Arg::with_name("page")
+                .short("p")
+                .long("page")
+                .takes_value(true)
+                .help("Only this page, counting from 1"))
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("tables")
+            .about("Tables as CSV")
+            .arg(clap::Arg::with_name("out")
+                .short("o")
+                .long("out")
+                .takes_value(true)
+                .help("Write each table to DIR/<file>-tableN.csv instead of printing"))
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("figures")
+            .about("Figure regions and their captions, as JSON")
+            .arg(clap::Arg::with_name("png")
+                .long("png")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Render each figure to DIR/<file>-figureN.png"))
+            .arg(clap::Arg::with_name("scale")
+                .long("scale")
+                .takes_value(true)
+                .default_value("2.0")
+                .help("Pixels per point of the rendered figures"))
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .subcommand(clap::SubCommand::with_name("footnotes")
+            .about("Footnotes and the places that refer to them, as JSON")
+            .arg(clap::Arg::with_name("FILE")
+                .help("Name of input file")
+                .required(true)
+                .index(1)))
+        .get_matches();
+
+    match matches.subcommand() {
+        ("refs", Some(m)) => {
+            match refs(m.value_of("FILE").unwrap()) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("graph", Some(m)) => citation_graph(m.value_of("DIR").unwrap(), m.value_of("format").unwrap()),
+        ("enrich", Some(m)) => enrich(m.value_of("PATH").unwrap(), m.value_of("arxiv-dump"), m.value_of("crossref")),
+        ("reconcile", Some(m)) => reconcile(m.value_of("BIB").unwrap(), m.value_of("DIR").unwrap(), m.value_of("write-bib")),
+        ("index", Some(m)) => build_index(m.value_of("DIR").unwrap(), m.value_of("index").unwrap()),
+        ("search", Some(m)) => search_index(m.value_of("QUERY").unwrap(), m.value_of("index").unwrap()),
+        ("watch", Some(m)) => watch_inbox(m.value_of("DIR").unwrap(), m.value_of("index").unwrap(),
+            m.value_of("library"), value_t!(m, "settle", u64).unwrap_or_else(|e| e.exit())),
+        ("organize", Some(m)) => {
+            if m.is_present("undo") {
+                undo_organize(m.value_of("journal").unwrap());
+            }
+            else {
+                organize_library(m.value_of("SRC").unwrap(), m.value_of("DEST").unwrap(), m.value_of("layout").unwrap(),
+                    organize::Mode::from_str(m.value_of("mode").unwrap()).unwrap(),
+                    organize::Conflict::from_str(m.value_of("on-conflict").unwrap()).unwrap(),
+                    m.is_present("dry-run"), m.value_of("journal").unwrap());
+            }
+        },
+        ("tag", Some(m)) => tag_files(m, m.value_of("index").unwrap()),
+        ("export", Some(m)) => export(m.value_of("FILTER").unwrap(), m.value_of("index").unwrap()),
+        ("annots", Some(m)) => list_annotations(m.value_of("PATH").unwrap(), m.value_of("format").unwrap()),
+        ("text", Some(m)) => {
+            match page_texts(m.value_of("FILE").unwrap(), m.is_present("all"), m) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("blocks", Some(m)) => {
+            match page_blocks(m.value_of("FILE").unwrap(), &block_config(m), m) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("outline", Some(m)) => {
+            match document_outline(m.value_of("FILE").unwrap()) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("words", Some(m)) => {
+            let page = if m.is_present("page") {
+                Some(value_t!(m, "page", usize).unwrap_or_else(|e| e.exit()))
+            }
+            else {
+                None
+            };
+            match page_words(m.value_of("FILE").unwrap(), page) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("tables", Some(m)) => {
+            match document_tables(m.value_of("FILE").unwrap(), m.value_of("out")) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("figures", Some(m)) => {
+            let scale = value_t!(m, "scale", f64).unwrap_or_else(|e| e.exit());
+            match document_figures(m.value_of("FILE").unwrap(), m.value_of("png"), scale) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        ("footnotes", Some(m)) => {
+            match list_footnotes(m.value_of("FILE").unwrap()) {
+                Ok(()) => (),
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                }
+            }
+        },
+        _ => classify_files(matches.value_of("FILE").unwrap(), matches.is_present("recursive"))
+    }
 
     //match run(&name) {
     //    Ok(()) => (),
@@ -180,4 +1619,3 @@ fn main() {
     //    }
     //};
 }
-
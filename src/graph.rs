@@ -0,0 +1,199 @@
+use crate::item::Item;
+use crate::references::Citation;
+
+pub struct Node {
+    pub file: String,
+    pub item: Item,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Match {
+    Id,
+    Title,
+}
+
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    // citing node, cited node
+    pub edges: Vec<(usize, usize, Match)>,
+}
+
+fn normalize_title(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_alphanumeric() { c.to_lowercase().next().unwrap() } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bigrams(s: &str) -> Vec<(char, char)> {
+    let c = s.chars().collect::<Vec<_>>();
+    let mut r = c.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>();
+    r.sort();
+    r
+}
+
+// Dice coefficient over character bigrams of the normalised titles
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a = bigrams(&normalize_title(a));
+    let b = bigrams(&normalize_title(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0
+    }
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            common += 1;
+            i += 1;
+            j += 1;
+        }
+        else if a[i] < b[j] {
+            i += 1;
+        }
+        else {
+            j += 1;
+        }
+    }
+    2.0 * common as f64 / (a.len() + b.len()) as f64
+}
+
+fn year_compatible(a: Option<u32>, b: Option<u32>) -> bool {
+    match (a, b) {
+        (Some(x), Some(y)) => (x as i64 - y as i64).abs() <= 1,
+        _ => true
+    }
+}
+
+// identifiers first, then title and year
+pub fn match_citation(c: &Citation, nodes: &[Node], citing: usize) -> Option<(usize, Match)> {
+    for (i, n) in nodes.iter().enumerate() {
+        if i == citing {
+            continue
+        }
+        let hit = c.ids.iter().any(|x| {
+            let x = x.clone().normalize();
+            n.item.own_ids().any(|y| x.same_work(y))
+        });
+        if hit {
+            return Some((i, Match::Id))
+        }
+    }
+    let title = c.title.as_ref()?;
+    let mut best = None;
+    let mut best_score = 0.85;
+    for (i, n) in nodes.iter().enumerate() {
+        if i == citing || n.item.title.is_empty() {
+            continue
+        }
+        if !year_compatible(c.year, n.item.pub_date.map(|x| x.year)) {
+            continue
+        }
+        let score = title_similarity(title, &n.item.title);
+        if score >= best_score {
+            best_score = score;
+            best = Some((i, Match::Title));
+        }
+    }
+    best
+}
+
+pub fn build(nodes: Vec<Node>, refs: &[Vec<Citation>]) -> Graph {
+    let mut edges: Vec<(usize, usize, Match)> = vec![];
+    for (i, cs) in refs.iter().enumerate() {
+        for c in cs {
+            if let Some((j, m)) = match_citation(c, &nodes, i) {
+                if !edges.iter().any(|&(a, b, _)| a == i && b == j) {
+                    edges.push((i, j, m));
+                }
+            }
+        }
+    }
+    Graph{nodes, edges}
+}
+
+fn label(n: &Node) -> String {
+    let name = if n.item.title.is_empty() {
+        std::path::Path::new(&n.file).file_name().and_then(|x| x.to_str()).unwrap_or(&n.file).to_string()
+    }
+    else {
+        n.item.title.clone()
+    };
+    match n.item.pub_date {
+        Some(d) => format!("{} ({})", name, d.year),
+        None => name
+    }
+}
+
+// "doi:10.1000/x arxiv:1501.00001", the primary id first
+fn ids(n: &Node) -> String {
+    let mut r = n.item.primary_id().into_iter().collect::<Vec<_>>();
+    r.extend(n.item.ids.iter().map(|x| &x.id).filter(|&x| Some(x) != n.item.primary_id()));
+    r.iter().map(|x| format!("{}:{}", x.kind(), x.value())).collect::<Vec<_>>().join(" ")
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn to_dot(g: &Graph) -> String {
+    let mut r = String::from("digraph citations {\n");
+    for (i, n) in g.nodes.iter().enumerate() {
+        r += &format!("  n{} [label=\"{}\", tooltip=\"{}\", ids=\"{}\"];\n", i, dot_escape(&label(n)), dot_escape(&n.file),
+                      dot_escape(&ids(n)));
+    }
+    for &(a, b, m) in &g.edges {
+        let style = if m == Match::Title { " [style=dashed]" } else { "" };
+        r += &format!("  n{} -> n{}{};\n", a, b, style);
+    }
+    r += "}\n";
+    r
+}
+
+pub fn to_graphml(g: &Graph) -> String {
+    let mut r = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    r += "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n";
+    r += "  <key id=\"file\" for=\"node\" attr.name=\"file\" attr.type=\"string\"/>\n";
+    r += "  <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n";
+    r += "  <key id=\"year\" for=\"node\" attr.name=\"year\" attr.type=\"int\"/>\n";
+    r += "  <key id=\"ids\" for=\"node\" attr.name=\"ids\" attr.type=\"string\"/>\n";
+    r += "  <key id=\"match\" for=\"edge\" attr.name=\"match\" attr.type=\"string\"/>\n";
+    r += "  <graph id=\"citations\" edgedefault=\"directed\">\n";
+    for (i, n) in g.nodes.iter().enumerate() {
+        r += &format!("    <node id=\"n{}\">\n", i);
+        r += &format!("      <data key=\"file\">{}</data>\n", xml_escape(&n.file));
+        r += &format!("      <data key=\"title\">{}</data>\n", xml_escape(&n.item.title));
+        if let Some(d) = n.item.pub_date {
+            r += &format!("      <data key=\"year\">{}</data>\n", d.year);
+        }
+        if !n.item.ids.is_empty() {
+            r += &format!("      <data key=\"ids\">{}</data>\n", xml_escape(&ids(n)));
+        }
+        r += "    </node>\n";
+    }
+    for &(a, b, m) in &g.edges {
+        let m = if m == Match::Id { "id" } else { "title" };
+        r += &format!("    <edge source=\"n{}\" target=\"n{}\"><data key=\"match\">{}</data></edge>\n", a, b, m);
+    }
+    r += "  </graph>\n</graphml>\n";
+    r
+}
+
+pub fn cited_by(g: &Graph) -> String {
+    let mut r = String::new();
+    for (i, n) in g.nodes.iter().enumerate() {
+        let citing = g.edges.iter().filter(|x| x.1 == i).map(|x| x.0).collect::<Vec<_>>();
+        if citing.is_empty() {
+            continue
+        }
+        r += &format!("{}\n", n.file);
+        for j in citing {
+            r += &format!("  cited by: {}\n", g.nodes[j].file);
+        }
+    }
+    r
+}
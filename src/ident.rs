@@ -0,0 +1,172 @@
+use crate::item::GlobalId;
+use crate::thesis::find_ci;
+
+fn digits(s: &str) -> usize {
+    s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len())
+}
+
+fn trim_punct(s: &str) -> &str {
+    s.trim_end_matches(|c| c == '.' || c == ',' || c == ';' || c == ':' || c == ')' || c == ']' || c == '>' || c == '"')
+}
+
+// 10.<registrant>/<suffix>
+pub fn dois(s: &str) -> Vec<String> {
+    let mut r = vec![];
+    let mut from = 0;
+    while let Some(i) = s[from..].find("10.") {
+        let start = from + i;
+        from = start + 3;
+        if s[..start].chars().last().map_or(false, |c| c.is_ascii_digit() || c == '.') {
+            continue
+        }
+        let rest = &s[start + 3..];
+        let n = digits(rest);
+        if n < 4 || n > 9 {
+            continue
+        }
+        let rest = &rest[n..];
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if !rest.starts_with('/') {
+            continue
+        }
+        let end = rest.find(|c: char| c.is_whitespace()).unwrap_or(rest.len());
+        let len = (s.len() - start) - (rest.len() - end);
+        let doi = trim_punct(&s[start .. start + len]);
+        if doi.len() > doi.find('/').unwrap() + 1 {
+            r.push(String::from(doi));
+        }
+    }
+    r
+}
+
+// 1501.00001, 1501.00001v2, math.AG/0601001, hep-th/9901001v3
+pub fn arxiv_token(s: &str) -> Option<&str> {
+    let n = digits(s);
+    let end = if n == 4 && s[4..].starts_with('.') {
+        let m = digits(&s[5..]);
+        if m < 4 || m > 5 {
+            return None
+        }
+        5 + m
+    }
+    else {
+        let a = s.find('/')?;
+        let archive = &s[..a];
+        if archive.is_empty() || !archive.chars().all(|c| c.is_ascii_alphabetic() || c == '-' || c == '.') {
+            return None
+        }
+        if digits(&s[a+1..]) != 7 {
+            return None
+        }
+        a + 8
+    };
+    let rest = &s[end..];
+    let v = if rest.starts_with('v') { digits(&rest[1..]) } else { 0 };
+    Some(if v > 0 { &s[..end + 1 + v] } else { &s[..end] })
+}
+
+// "arXiv:1501.00001v2 [math.AG] 3 Jan 2015"
+pub fn arxiv_ids(s: &str) -> Vec<String> {
+    let mut r = vec![];
+    let mut from = 0;
+    while let Some(i) = find_ci(&s[from..], "arxiv") {
+        from += i + "arxiv".len();
+        let rest = s[from..].trim_start_matches(|c: char| c == ':' || c == ' ');
+        if let Some(x) = arxiv_token(rest) {
+            r.push(String::from(x));
+        }
+    }
+    r
+}
+
+// "ISBN 978-3-642-00000-0", "ISBN: 0-387-00000-X" or "ISBN-13: 978-..."
+pub fn isbns(s: &str) -> Vec<String> {
+    let mut r = vec![];
+    let mut from = 0;
+    while let Some(i) = find_ci(&s[from..], "isbn") {
+        from += i + "isbn".len();
+        let rest = s[from..].trim_start_matches(|c: char| c == ':' || c == ' ' || c == '-');
+        // "ISBN-13: ..."
+        let rest = if rest.starts_with("13:") || rest.starts_with("10:") { &rest[3..] } else { rest };
+        let rest = rest.trim_start();
+        let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '-' || c == 'X')).unwrap_or(rest.len());
+        let isbn = rest[..end].trim_end_matches('-').replace("-", "");
+        if isbn.len() == 10 || isbn.len() == 13 {
+            r.push(isbn);
+        }
+    }
+    r
+}
+
+pub fn from_text(s: &str) -> Vec<GlobalId> {
+    let mut r = vec![];
+    for x in arxiv_ids(s) {
+        r.push(GlobalId::ARXIV(x));
+    }
+    for x in dois(s) {
+        r.push(GlobalId::DOI(x));
+    }
+    for x in isbns(s) {
+        r.push(GlobalId::ISBN(x));
+    }
+    r
+}
+
+// XMP packets carry the same identifiers as text: prism:doi, dc:identifier, arXiv ids in dc:description
+pub fn from_xmp(xmp: &str) -> Vec<GlobalId> {
+    from_text(xmp)
+}
+
+pub fn from_uri(uri: &str) -> Option<GlobalId> {
+    if let Some(i) = uri.find("doi.org/") {
+        return dois(&uri[i + "doi.org/".len()..]).into_iter().next().map(GlobalId::DOI)
+    }
+    if let Some(i) = uri.find("arxiv.org/") {
+        let rest = &uri[i + "arxiv.org/".len()..];
+        let rest = rest.trim_start_matches("abs/").trim_start_matches("pdf/");
+        return arxiv_token(rest).map(|x| GlobalId::ARXIV(String::from(x)))
+    }
+    if uri.contains("hal.") || uri.contains("hal-") {
+        let last = uri.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        let last = trim_punct(last);
+        if last.starts_with("hal-") || last.starts_with("tel-") || last.starts_with("inria-") {
+            return Some(GlobalId::HAL(String::from(last)))
+        }
+    }
+    if uri.contains("ssrn.com") {
+        if let Some(i) = uri.find("abstract") {
+            let rest = uri[i..].trim_start_matches(|c: char| c.is_alphabetic() || c == '_' || c == '=');
+            let n = digits(rest);
+            if n > 0 {
+                return Some(GlobalId::SSRN(String::from(&rest[..n])))
+            }
+        }
+    }
+    None
+}
+
+// "1501.00001v2.pdf", "10.1007_s00208-011-0654-3.pdf", "hal-01234567.pdf"
+pub fn from_filename(file: &str) -> Vec<GlobalId> {
+    let path = std::path::Path::new(file);
+    let stem = match path.file_stem().and_then(|x| x.to_str()) {
+        Some(x) => x,
+        None => return vec![]
+    };
+    let mut r = vec![];
+    if let Some(x) = arxiv_token(stem) {
+        if x.len() == stem.len() {
+            r.push(GlobalId::ARXIV(String::from(x)));
+        }
+    }
+    if stem.starts_with("10.") {
+        let s = stem.replacen("%2F", "/", 1).replacen("%2f", "/", 1);
+        let s = if s.contains('/') { s } else { s.replacen("_", "/", 1) };
+        for x in dois(&s) {
+            r.push(GlobalId::DOI(x));
+        }
+    }
+    if stem.starts_with("hal-") && digits(&stem[4..]) == 8 {
+        r.push(GlobalId::HAL(String::from(&stem[..12])));
+    }
+    r
+}
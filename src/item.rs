@@ -1,39 +1,278 @@
 
 
-#[derive(Debug)]
+#[derive(Debug,Clone)]
 pub struct Author {
     pub first_name: String,
     pub last_name: String,
 }
 
-#[derive(Debug)]
+impl Author {
+    // accepts both "Last, First" and "First Last"
+    pub fn from_name(s: &str) -> Author {
+        let s = s.trim();
+        if let Some(i) = s.find(',') {
+            return Author {
+                first_name: String::from(s[i+1..].trim()),
+                last_name: String::from(s[..i].trim())
+            }
+        }
+        match s.rfind(' ') {
+            Some(i) => Author {
+                first_name: String::from(s[..i].trim()),
+                last_name: String::from(s[i+1..].trim())
+            },
+            None => Author {
+                first_name: String::new(),
+                last_name: String::from(s)
+            }
+        }
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
 pub enum GlobalId {
     DOI(String),
     ISBN(String),
     ARXIV(String),
     CITEER(String),
     HAL(String),
+    SSRN(String),
+}
+
+// "1501.00001v2" -> ("1501.00001", Some(2))
+pub fn split_version(id: &str) -> (&str, Option<u32>) {
+    match id.rfind('v') {
+        Some(i) if i > 0 && i + 1 < id.len() && id[i+1..].chars().all(|c| c.is_ascii_digit()) => {
+            (&id[..i], id[i+1..].parse::<u32>().ok())
+        },
+        _ => (id, None)
+    }
+}
+
+impl GlobalId {
+    // DOIs are case-insensitive, arXiv ids may keep their "arXiv:" prefix
+    pub fn normalize(self) -> GlobalId {
+        match self {
+            GlobalId::DOI(x) => GlobalId::DOI(x.trim().to_lowercase()),
+            GlobalId::ARXIV(x) => {
+                let x = x.trim();
+                let x = if x.to_lowercase().starts_with("arxiv:") { &x[6..] } else { x };
+                GlobalId::ARXIV(String::from(x))
+            },
+            GlobalId::ISBN(x) => GlobalId::ISBN(x.replace("-", "")),
+            x => x
+        }
+    }
+
+    // "doi", "arxiv", ...
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            GlobalId::DOI(_) => "doi",
+            GlobalId::ISBN(_) => "isbn",
+            GlobalId::ARXIV(_) => "arxiv",
+            GlobalId::CITEER(_) => "citeseerx",
+            GlobalId::HAL(_) => "hal",
+            GlobalId::SSRN(_) => "ssrn",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match *self {
+            GlobalId::DOI(ref x) | GlobalId::ISBN(ref x) | GlobalId::ARXIV(ref x) |
+            GlobalId::CITEER(ref x) | GlobalId::HAL(ref x) | GlobalId::SSRN(ref x) => x
+        }
+    }
+
+    // different versions of an arXiv preprint are the same work
+    pub fn same_work(&self, other: &GlobalId) -> bool {
+        match (self, other) {
+            (&GlobalId::ARXIV(ref a), &GlobalId::ARXIV(ref b)) => split_version(a).0 == split_version(b).0,
+            _ => self == other
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum IdSource {
+    // anywhere in the text of the first pages, possibly a cited work's
+    Text,
+    // where the publisher or server prints the item's own: cover pages, the arXiv stamp,
+    // running heads and footers
+    Stamp,
+    Xmp,
+    Filename,
+    Link,
+    // an external metadata dump or service
+    Lookup,
+}
+
+impl IdSource {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            IdSource::Text => "text",
+            IdSource::Stamp => "stamp",
+            IdSource::Xmp => "xmp",
+            IdSource::Filename => "filename",
+            IdSource::Link => "link",
+            IdSource::Lookup => "lookup",
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct Identifier {
+    pub id: GlobalId,
+    pub source: IdSource,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,PartialOrd)]
+pub enum Precision {
+    Year,
+    Month,
+    Day,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Date {
+    pub year: u32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+impl Date {
+    pub fn year(year: u32) -> Date {
+        Date{year, month: None, day: None}
+    }
+
+    pub fn precision(&self) -> Precision {
+        match (self.month, self.day) {
+            (Some(_), Some(_)) => Precision::Day,
+            (Some(_), None) => Precision::Month,
+            _ => Precision::Year
+        }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.month, self.day) {
+            (Some(m), Some(d)) => write!(f, "{}-{:02}-{:02}", self.year, m, d),
+            (Some(m), None) => write!(f, "{}-{:02}", self.year, m),
+            _ => write!(f, "{}", self.year)
+        }
+    }
+}
+
+#[derive(Debug,Default,Clone)]
+pub struct JournalRef {
+    pub journal: String,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub first_page: Option<String>,
+    pub last_page: Option<String>,
+    pub year: Option<u32>,
+}
+
+#[derive(Debug,Default,Clone)]
+pub struct History {
+    pub received: Option<Date>,
+    pub revised: Option<Date>,
+    pub accepted: Option<Date>,
+    pub published: Option<Date>,
+}
+
+// a field an external source gives differently; the extracted value is kept
+#[derive(Debug,Clone)]
+pub struct Disagreement {
+    pub field: &'static str,
+    pub extracted: String,
+    // "crossref"
+    pub source: &'static str,
+    pub value: String,
+}
+
+#[derive(Debug,Default)]
+pub struct Thesis {
+    pub degree: Option<String>,
+    pub institution: Option<String>,
+    pub department: Option<String>,
+    pub supervisor: Option<String>,
+    pub year: Option<u32>,
 }
 
-#[derive(Debug)]
+#[derive(Debug,Default)]
 pub struct Item {
     pub title: String,
     pub authors: Vec<Author>,
     pub publisher: Option<String>,
-    pub pub_date: Option<u32>,
-    pub global_id: Option<GlobalId>
+    pub pub_date: Option<Date>,
+    pub journal: Option<JournalRef>,
+    pub history: History,
+    pub ids: Vec<Identifier>,
+    pub primary: Option<usize>,
+    pub thesis: Option<Thesis>,
+    pub abstract_text: Option<String>,
+    pub categories: Vec<String>,
+    pub disagreements: Vec<Disagreement>
 }
 
-#[derive(Debug)]
+impl Item {
+    // the first identifier added becomes the primary one, unless it was only found in the text
+    pub fn add_id(&mut self, id: GlobalId, source: IdSource) {
+        let id = id.normalize();
+        if let Some(x) = self.ids.iter_mut().find(|x| x.id == id) {
+            // the same id seen again in a place that makes it ours
+            if x.source == IdSource::Text {
+                x.source = source;
+            }
+            return
+        }
+        if self.primary.is_none() && source != IdSource::Text {
+            self.primary = Some(self.ids.len());
+        }
+        self.ids.push(Identifier{id, source});
+    }
+
+    pub fn primary_id(&self) -> Option<&GlobalId> {
+        self.primary.map(|i| &self.ids[i].id)
+    }
+
+    pub fn set_primary<F: Fn(&GlobalId) -> bool>(&mut self, f: F) {
+        if let Some(i) = self.ids.iter().position(|x| x.source != IdSource::Text && f(&x.id)) {
+            self.primary = Some(i);
+        }
+    }
+
+    // the ids that name this item; ones only found in the text are left out of matching and
+    // lookups
+    pub fn own_ids(&self) -> impl Iterator<Item = &GlobalId> {
+        self.ids.iter().filter(|x| x.source != IdSource::Text).map(|x| &x.id)
+    }
+
+    pub fn same_work(&self, other: &Item) -> bool {
+        self.own_ids().any(|x| other.own_ids().any(|y| x.same_work(y)))
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
 pub enum ItemType {
     Unknown,
     Named,
     Arxiv,
     Springer,
+    Thesis,
+    Hal,
+    CiteSeerX,
+    BioRxiv,
+    MedRxiv,
+    Ssrn,
 }
 
-static ITEM_TYPES: [(ItemType,&str); 4] = [(ItemType::Unknown, "unknown: "), (ItemType::Named, "named: "),
-                            (ItemType::Arxiv, "arxiv: "), (ItemType::Springer, "springer: ")];
+static ITEM_TYPES: [(ItemType,&str); 10] = [(ItemType::Unknown, "unknown: "), (ItemType::Named, "named: "),
+                            (ItemType::Arxiv, "arxiv: "), (ItemType::Springer, "springer: "),
+                            (ItemType::Thesis, "thesis: "), (ItemType::Hal, "hal: "),
+                            (ItemType::CiteSeerX, "citeseerx: "), (ItemType::BioRxiv, "biorxiv: "),
+                            (ItemType::MedRxiv, "medrxiv: "), (ItemType::Ssrn, "ssrn: ")];
 
 pub fn item_type(s: &str) -> Option<(&ItemType,&str)> {
     for &(ref i, ref m) in ITEM_TYPES.iter() {
@@ -44,3 +283,12 @@ pub fn item_type(s: &str) -> Option<(&ItemType,&str)> {
     return None
 }
 
+// "arxiv", "springer", ...
+pub fn type_name(t: &ItemType) -> &'static str {
+    for &(ref i, m) in ITEM_TYPES.iter() {
+        if i == t {
+            return m.trim_end_matches(": ")
+        }
+    }
+    "unknown"
+}
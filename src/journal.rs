@@ -0,0 +1,345 @@
+use crate::item::{Date, History, JournalRef};
+use crate::thesis::{find_ci, find_year};
+
+#[derive(Debug,Clone,PartialEq)]
+enum Tok {
+    Num(String),
+    Word(String),
+    Punct(char),
+}
+
+fn tokenize(s: &str) -> Vec<Tok> {
+    let mut r = vec![];
+    let mut cur = String::new();
+    let mut num = false;
+    for c in s.chars() {
+        if c.is_alphanumeric() && !cur.is_empty() && c.is_ascii_digit() == num {
+            cur.push(c);
+            continue
+        }
+        if !cur.is_empty() {
+            r.push(if num { Tok::Num(cur) } else { Tok::Word(cur) });
+            cur = String::new();
+        }
+        if c.is_alphanumeric() {
+            num = c.is_ascii_digit();
+            cur.push(c);
+        }
+        else if c == '–' || c == '—' || c == '‐' || c == '−' {
+            r.push(Tok::Punct('-'));
+        }
+        else if !c.is_whitespace() {
+            r.push(Tok::Punct(c));
+        }
+    }
+    if !cur.is_empty() {
+        r.push(if num { Tok::Num(cur) } else { Tok::Word(cur) });
+    }
+    r
+}
+
+static MONTHS: [&str; 12] = ["january", "february", "march", "april", "may", "june",
+                             "july", "august", "september", "october", "november", "december"];
+static GERMAN_MONTHS: [&str; 12] = ["januar", "februar", "märz", "april", "mai", "juni",
+                                    "juli", "august", "september", "oktober", "november", "dezember"];
+
+// full or abbreviated, English or German: "Mar.", "Sept", "Mai", "März", "Okt"
+fn month(w: &str) -> Option<u32> {
+    let w = w.to_lowercase();
+    if w.chars().count() < 3 {
+        return None
+    }
+    let w = match w.as_str() {
+        "sept" => String::from("sep"),
+        "maerz" | "mrz" => String::from("märz"),
+        _ => w
+    };
+    MONTHS.iter().position(|m| m.starts_with(&w))
+        .or_else(|| GERMAN_MONTHS.iter().position(|m| m.starts_with(&w)))
+        .map(|x| x as u32 + 1)
+}
+
+fn is_year(s: &str) -> bool {
+    s.len() == 4 && s.parse::<u32>().map(|y| y >= 1800 && y <= 2100).unwrap_or(false)
+}
+
+fn num(t: Option<&Tok>) -> Option<u32> {
+    match t {
+        Some(&Tok::Num(ref x)) => x.parse::<u32>().ok(),
+        _ => None
+    }
+}
+
+fn word_month(t: Option<&Tok>) -> Option<u32> {
+    match t {
+        Some(&Tok::Word(ref x)) => month(x),
+        _ => None
+    }
+}
+
+fn day(d: u32) -> Option<u32> {
+    if d >= 1 && d <= 31 { Some(d) } else { None }
+}
+
+// a date at the start of the token stream
+fn date_at(t: &[Tok]) -> Option<Date> {
+    let n0 = num(t.get(0));
+    // "2010-05-03", "2010/05/03"
+    if let Some(y) = n0.filter(|&y| is_year(&y.to_string())) {
+        let sep = t.get(1);
+        if sep == Some(&Tok::Punct('-')) || sep == Some(&Tok::Punct('/')) {
+            if let Some(m) = num(t.get(2)).filter(|&m| m >= 1 && m <= 12) {
+                let d = if t.get(3) == sep { num(t.get(4)).and_then(day) } else { None };
+                return Some(Date{year: y, month: Some(m), day: d})
+            }
+        }
+        return Some(Date::year(y))
+    }
+    // "3 May 2010", "3. Mai 2010"
+    if let Some(d) = n0.and_then(day) {
+        let t1 = if t.get(1) == Some(&Tok::Punct('.')) { &t[2..] } else { &t[1..] };
+        if let Some(m) = word_month(t1.get(0)) {
+            let t2 = if t1.get(1) == Some(&Tok::Punct('.')) || t1.get(1) == Some(&Tok::Punct(',')) { &t1[2..] } else { &t1[1..] };
+            if let Some(y) = num(t2.get(0)).filter(|&y| is_year(&y.to_string())) {
+                return Some(Date{year: y, month: Some(m), day: Some(d)})
+            }
+        }
+        // "03.05.2010"
+        if t.get(1) == Some(&Tok::Punct('.')) && t.get(3) == Some(&Tok::Punct('.')) {
+            if let (Some(m), Some(y)) = (num(t.get(2)), num(t.get(4))) {
+                if m >= 1 && m <= 12 && is_year(&y.to_string()) {
+                    return Some(Date{year: y, month: Some(m), day: Some(d)})
+                }
+            }
+        }
+        return None
+    }
+    // "May 3, 2010", "May 2010", "Mar. 13, 2020"
+    if let Some(m) = word_month(t.get(0)) {
+        let t1 = if t.get(1) == Some(&Tok::Punct('.')) { &t[2..] } else { &t[1..] };
+        let n1 = num(t1.get(0))?;
+        if is_year(&n1.to_string()) {
+            return Some(Date{year: n1, month: Some(m), day: None})
+        }
+        let d = day(n1)?;
+        let t2 = if t1.get(1) == Some(&Tok::Punct(',')) { &t1[2..] } else { &t1[1..] };
+        let y = num(t2.get(0)).filter(|&y| is_year(&y.to_string()))?;
+        return Some(Date{year: y, month: Some(m), day: Some(d)})
+    }
+    None
+}
+
+// the first date within the first few tokens of `s`
+pub fn parse_date(s: &str) -> Option<Date> {
+    let t = tokenize(s);
+    for i in 0 .. std::cmp::min(t.len(), 6) {
+        if let Some(d) = date_at(&t[i..]) {
+            return Some(d)
+        }
+    }
+    None
+}
+
+static HISTORY: [(&str, usize); 9] = [
+    ("received in revised form", 1), ("received", 0), ("revised", 1), ("accepted", 2),
+    ("published online", 3), ("available online", 3), ("first online", 3), ("published", 3),
+    ("online publication", 3),
+];
+
+// a history entry starts a line or follows a ';' or '/' of the same block
+fn entry_start(before: &str) -> bool {
+    match before.trim_end_matches(|c: char| c.is_whitespace() && c != '\n').chars().last() {
+        None | Some('\n') | Some(';') | Some('/') => true,
+        _ => false
+    }
+}
+
+// the date right after the keyword, "Received 3 May 2010" or "Received: 3 May 2010"
+fn entry_date(rest: &str) -> Option<Date> {
+    let rest = &rest[..rest.char_indices().nth(40).map(|(j, _)| j).unwrap_or(rest.len())];
+    let t = tokenize(rest);
+    let t = if t.get(0) == Some(&Tok::Punct(':')) { &t[1..] } else { &t[..] };
+    date_at(t)
+}
+
+// "Received: 3 May 2010 / Accepted: 12 June 2010 / Published online: 1 July 2010"
+// "Received 3 May 2010; received in revised form 5 June 2010; accepted 12 June 2010"
+pub fn history(text: &str) -> History {
+    let mut r = History::default();
+    for &(key, slot) in HISTORY.iter() {
+        let mut from = 0;
+        while let Some(i) = find_ci(&text[from..], key) {
+            let start = from + i;
+            from = start + key.len();
+            if !entry_start(&text[..start]) {
+                continue
+            }
+            if let Some(d) = entry_date(&text[from..]) {
+                let field = match slot {
+                    0 => &mut r.received,
+                    1 => &mut r.revised,
+                    2 => &mut r.accepted,
+                    _ => &mut r.published
+                };
+                if field.is_none() {
+                    *field = Some(d);
+                }
+                break
+            }
+        }
+    }
+    r
+}
+
+fn pages_from(t: &[Tok], i: usize, r: &mut JournalRef) -> usize {
+    let p1 = match t.get(i) { Some(&Tok::Num(ref x)) => x.clone(), _ => return i };
+    r.first_page = Some(p1);
+    if t.get(i + 1) == Some(&Tok::Punct('-')) {
+        if let Some(&Tok::Num(ref x)) = t.get(i + 2) {
+            r.last_page = Some(x.clone());
+            return i + 3
+        }
+    }
+    i + 1
+}
+
+// "254 (2008) 1234–1260", "(2011) 350:1–25", "265, 1–20 (2010)", "12(3), 45–67 (2005)",
+// "362 (2010), no. 3, 1234–1256"
+fn parse_tail(t: &[Tok], r: &mut JournalRef) {
+    let mut i = 0;
+    while i < t.len() {
+        match (&t[i], t.get(i + 1), t.get(i + 2)) {
+            (&Tok::Punct('('), Some(&Tok::Num(ref x)), Some(&Tok::Punct(')'))) => {
+                if is_year(x) && r.year.is_none() {
+                    r.year = x.parse::<u32>().ok();
+                }
+                else if r.volume.is_some() && r.issue.is_none() {
+                    r.issue = Some(x.clone());
+                }
+                i += 3;
+            },
+            (&Tok::Num(ref x), Some(&Tok::Punct(':')), Some(&Tok::Num(_))) => {
+                r.volume = Some(x.clone());
+                i = pages_from(t, i + 2, r);
+            },
+            (&Tok::Num(_), Some(&Tok::Punct('-')), Some(&Tok::Num(_))) => {
+                i = pages_from(t, i, r);
+            },
+            (&Tok::Word(ref w), _, _) => {
+                let w = w.to_lowercase();
+                if w == "doi" || w == "http" || w == "https" {
+                    break
+                }
+                let next = if t.get(i + 1) == Some(&Tok::Punct('.')) { i + 2 } else { i + 1 };
+                if w == "no" || w == "issue" || w == "nr" || w == "number" {
+                    if let Some(&Tok::Num(ref x)) = t.get(next) {
+                        r.issue = Some(x.clone());
+                        i = next + 1;
+                        continue
+                    }
+                }
+                if w == "pp" || w == "p" || w == "pages" {
+                    i = pages_from(t, next, r);
+                    continue
+                }
+                if w == "vol" || w == "volume" {
+                    if let Some(&Tok::Num(ref x)) = t.get(next) {
+                        r.volume = Some(x.clone());
+                        i = next + 1;
+                        continue
+                    }
+                }
+                if month(&w).is_some() {
+                    if let Some(&Tok::Num(ref x)) = t.get(next) {
+                        if is_year(x) && r.year.is_none() {
+                            r.year = x.parse::<u32>().ok();
+                        }
+                    }
+                }
+                i += 1;
+            },
+            (&Tok::Num(ref x), _, _) => {
+                if r.volume.is_none() && !is_year(x) {
+                    r.volume = Some(x.clone());
+                }
+                else if r.first_page.is_none() && !is_year(x) {
+                    r.first_page = Some(x.clone());
+                }
+                i += 1;
+            },
+            _ => i += 1
+        }
+    }
+}
+
+fn clean_name(s: &str) -> &str {
+    s.trim().trim_end_matches(|c: char| c == ',' || c == ';' || c.is_whitespace())
+}
+
+// one header/footer line: "J. Funct. Anal. 254 (2008) 1234–1260", "Math. Ann. (2011) 350:1–25"
+pub fn journal_line(line: &str) -> Option<JournalRef> {
+    let line = line.trim();
+    let mut split = line.find(|c: char| c.is_ascii_digit() || c == '(')?;
+    // series numbers belong to the name: "Ann. of Math. (2) 171 (2010), 1–20"
+    let head = line[split..].chars().take(4).collect::<Vec<_>>();
+    if head.len() == 4 && head[0] == '(' && head[1].is_ascii_digit() && head[2] == ')' {
+        split += 3;
+    }
+    let name = clean_name(&line[..split]);
+    if !name.chars().any(|c| c.is_alphabetic()) || name.contains(':') || name.chars().count() > 100
+        || name.split_whitespace().count() > 12 {
+        return None
+    }
+    let t = tokenize(&line[split..]);
+    if !t.iter().any(|x| *x == Tok::Punct('(')) {
+        return None
+    }
+    let mut r = JournalRef::default();
+    r.journal = String::from(name);
+    parse_tail(&t, &mut r);
+    if r.year.is_some() && r.first_page.is_some() {
+        Some(r)
+    }
+    else {
+        None
+    }
+}
+
+// AMS style header: "Volume 23, Number 2, April 2010, Pages 345–400" below the journal name
+fn ams_line(name: &str, line: &str) -> Option<JournalRef> {
+    let line = line.trim();
+    if !(line.starts_with("Volume") || line.starts_with("VOLUME")) {
+        return None
+    }
+    let mut r = JournalRef::default();
+    r.journal = String::from(clean_name(name));
+    parse_tail(&tokenize(line), &mut r);
+    if r.year.is_none() {
+        r.year = find_year(line);
+    }
+    if r.volume.is_some() && !r.journal.is_empty() {
+        Some(r)
+    }
+    else {
+        None
+    }
+}
+
+// journal citation from the first and last lines of a page (running head and footer)
+pub fn journal_ref(text: &str) -> Option<JournalRef> {
+    let lines = text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect::<Vec<_>>();
+    let n = lines.len();
+    let mut cand = (0 .. std::cmp::min(n, 6)).collect::<Vec<_>>();
+    cand.extend(n.saturating_sub(6) .. n);
+    for i in cand {
+        if i > 0 {
+            if let Some(r) = ams_line(lines[i-1], lines[i]) {
+                return Some(r)
+            }
+        }
+        if let Some(r) = journal_line(lines[i]) {
+            return Some(r)
+        }
+    }
+    None
+}
@@ -0,0 +1,362 @@
+// Page text split into lines with their bounding boxes and dominant font,
+// the same construction as process_page in zuzu.rs, and into words and characters
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+#[derive(Debug,Clone)]
+pub struct BBox {
+    pub top: f64,
+    pub left: f64,
+    pub bottom: f64,
+    pub right: f64
+}
+
+impl PartialEq for BBox {
+    fn eq(&self, other: &BBox) -> bool {
+        self.top == other.top &&
+        self.left == other.left &&
+        self.bottom == other.bottom &&
+        self.right == other.right
+    }
+}
+
+impl PartialOrd for BBox {
+    fn partial_cmp(&self, other: &BBox) -> Option<Ordering> {
+        let b1 = self; let b2 = other;
+        if b1.eq(b2) {
+            return Some(Ordering::Equal)
+        }
+        if (b1.right < b2.left) || (b2.right < b1.left) {
+            return None  //no horizontal overlap
+        }
+        if b1.bottom < b2.top {
+            return Some(Ordering::Less)  //no vertical overlap
+        }
+        if b1.top > b2.bottom {
+            return Some(Ordering::Greater)  //no vertical overlap
+        }
+        //overlap detected!
+        if b1.top < b2.top && b2.top < b1.bottom && b1.bottom < b2.bottom {
+            return Some(Ordering::Less)
+        }
+        if b2.top < b1.top && b1.top < b2.bottom && b2.bottom < b1.bottom {
+            return Some(Ordering::Greater)
+        }
+        return None
+    }
+}
+
+impl BBox {
+    pub fn width(&self) -> f64 {
+        self.bottom - self.top
+    }
+
+    pub fn length(&self) -> f64 {
+        self.right - self.left
+    }
+
+    pub fn dist(&self, other: &BBox) -> f64 {
+        if self.bottom < other.top {
+            other.top - self.bottom
+        }
+        else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct TT {
+    pub text: String,
+    pub b_box: BBox,
+    pub font_name: String,
+    pub font_size: f64
+}
+
+#[derive(Debug,Clone)]
+pub struct Char {
+    pub c: char,
+    pub b_box: BBox,
+    pub font_name: String,
+    pub font_size: f64,
+    // index into page_lines
+    pub line: usize
+}
+
+#[derive(Debug,Clone)]
+pub struct Word {
+    pub text: String,
+    pub b_box: BBox,
+    pub font_name: String,
+    pub font_size: f64,
+    // bottom of the glyph boxes; a fixed share of the font size below the real baseline,
+    // so it compares between words of one font (superscripts sit higher)
+    pub baseline: f64,
+    pub line: usize,
+    // indices into page_chars
+    pub chars: Range<usize>
+}
+
+// a horizontal gap wider than this share of the font size separates words even without a space
+const WORD_GAP: f64 = 0.3;
+
+// font name and size to a tenth of a point
+pub type FontKey = (String, i64);
+
+pub fn font_key(t: &TT) -> FontKey {
+    (t.font_name.clone(), (t.font_size * 10.0).round() as i64)
+}
+
+// the upper median; None for no values
+pub fn median(mut v: Vec<f64>) -> Option<f64> {
+    if v.is_empty() {
+        return None
+    }
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Some(v[v.len() / 2])
+}
+
+pub fn validate_page(s: &str, attr: &[poppler::TextAttr], layout: &[poppler::ffi::PopplerRectangle]) ->
+  Result<(),glib::error::Error> {
+    let char_cnt = s.chars().count();
+    let end_attr = attr[attr.len() - 1].end_index;
+    let layout_len = layout.len();
+    if (char_cnt == layout_len) && (char_cnt == end_attr + 1) {
+        Ok(())
+    }
+    else {
+        Err(glib::error::Error::new(glib::FileError::Failed,
+            "XXX-ginger: Page invariants are broken"))
+    }
+}
+
+fn intersect_intervals(x1: usize, y1: usize, x2: usize, y2: usize) -> usize {
+    assert!(x1 <= y1 && x2 <= y2);
+    if y1 < x2 {return 0}
+    if y2 < x1 {return 0}
+    if x1 < x2 {
+        if y1 < y2 {return y1 - x2 + 1}
+        return y2 - x2 + 1
+    }
+    if y1 > y2 {return y2 -x1 + 1}
+    return y1 - x1 + 1
+}
+
+pub fn font(attr: &[poppler::TextAttr], from: usize, to: usize) -> &poppler::TextAttr {
+    assert!(attr.len() > 0);
+    let mut acc = vec![];
+    for i in 0.. attr.len() {
+        let l = intersect_intervals(attr[i].start_index, attr[i].end_index, from, to);
+        acc.push((l,i));
+    }
+    acc.sort_by(|&(l1,_i1),&(l2,_i2)| l1.cmp(&l2));
+    &attr[acc[acc.len() - 1].1]
+}
+
+pub fn b_box(p: &[poppler::ffi::PopplerRectangle]) -> BBox {
+    assert!(p.len() > 0);
+    let mut top = p[0].y1;
+    let mut bottom = p[0].y2;
+    let mut left = p[0].x1;
+    let mut right = p[0].x2;
+    for i in 1..p.len() {
+        if top > p[i].y1 {top = p[i].y1}
+        if bottom < p[i].y2 {bottom = p[i].y2}
+        if left > p[i].x1 {left = p[i].x1}
+        if right < p[i].x2 {right = p[i].x2}
+    }
+    return BBox{left, top, right, bottom}
+}
+
+pub fn merge_boxes<'a, I: Iterator<Item = &'a BBox>>(mut boxes: I) -> BBox {
+    let b0 = boxes.next().unwrap();
+    let mut top = b0.top;
+    let mut bottom = b0.bottom;
+    let mut left = b0.left;
+    let mut right = b0.right;
+    for b in boxes {
+        if top > b.top {top = b.top}
+        if bottom < b.bottom {bottom = b.bottom}
+        if left > b.left {left = b.left}
+        if right < b.right {right = b.right}
+    }
+    return BBox{left,top,right,bottom}
+}
+
+fn font_name(a: &poppler::TextAttr) -> String {
+    match a.font_name {
+        Some(ref x) => String::from(x.as_str()),
+        None => String::new()
+    }
+}
+
+pub fn page_chars(doc: &poppler::PopplerDocument, num: usize) -> Result<Vec<Char>,glib::error::Error> {
+    let page = doc.get_page(num)?;
+    let text = page.get_text();
+    if text.is_empty() {
+        return Ok(vec![])
+    }
+    let layout = page.get_text_layout()?;
+    let attr = page.get_text_attributes();
+    validate_page(&text, &attr, &layout)?;
+
+    let mut line = 0;
+    let mut k = 0;
+    let mut chars = vec![];
+    for (i,c) in text.chars().enumerate() {
+        if c == '\n' {
+            line += 1;
+            continue
+        }
+        while k + 1 < attr.len() && attr[k].end_index < i {
+            k += 1;
+        }
+        let r = &layout[i];
+        chars.push(Char {
+            c,
+            b_box: BBox{left: r.x1, top: r.y1, right: r.x2, bottom: r.y2},
+            font_name: font_name(&attr[k]),
+            font_size: attr[k].font_size,
+            line
+        });
+    }
+    Ok(chars)
+}
+
+fn word(chars: &[Char], r: Range<usize>) -> Word {
+    let cs = &chars[r.clone()];
+    // the font of most characters
+    let mut fonts: Vec<(&str, f64, usize)> = vec![];
+    for ch in cs {
+        match fonts.iter_mut().find(|f| f.0 == ch.font_name && f.1 == ch.font_size) {
+            Some(f) => f.2 += 1,
+            None => fonts.push((&ch.font_name, ch.font_size, 1))
+        }
+    }
+    let f = fonts.iter().max_by_key(|f| f.2).unwrap();
+    let mut bottoms = cs.iter().map(|ch| ch.b_box.bottom).collect::<Vec<_>>();
+    bottoms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    Word {
+        text: cs.iter().map(|ch| ch.c).collect(),
+        b_box: merge_boxes(cs.iter().map(|ch| &ch.b_box)),
+        font_name: String::from(f.0),
+        font_size: f.1,
+        baseline: bottoms[bottoms.len() / 2],
+        line: cs[0].line,
+        chars: r
+    }
+}
+
+// split on whitespace, line ends and wide horizontal gaps
+pub fn words(chars: &[Char]) -> Vec<Word> {
+    let mut r = vec![];
+    let mut start: Option<usize> = None;
+    for i in 0 ..= chars.len() {
+        let split = match chars.get(i) {
+            None => true,
+            Some(ch) if ch.c.is_whitespace() => true,
+            Some(ch) => match start {
+                Some(_) => {
+                    let p = &chars[i - 1];
+                    p.line != ch.line || ch.b_box.right < p.b_box.left ||
+                        ch.b_box.left - p.b_box.right > WORD_GAP * p.font_size.max(ch.font_size)
+                },
+                None => false
+            }
+        };
+        if split {
+            if let Some(s) = start.take() {
+                r.push(word(chars, s .. i));
+            }
+        }
+        if i < chars.len() && !chars[i].c.is_whitespace() && start.is_none() {
+            start = Some(i);
+        }
+    }
+    r
+}
+
+pub fn page_words(doc: &poppler::PopplerDocument, num: usize) -> Result<Vec<Word>,glib::error::Error> {
+    Ok(words(&page_chars(doc, num)?))
+}
+
+// boxes of each occurrence of `needle`, one per line it spans; line ends are not characters,
+// so a DOI broken over two lines is still found
+pub fn locate(chars: &[Char], needle: &str) -> Vec<Vec<BBox>> {
+    let needle = needle.chars().collect::<Vec<_>>();
+    let mut r = vec![];
+    if needle.is_empty() {
+        return r
+    }
+    let mut i = 0;
+    while i + needle.len() <= chars.len() {
+        if !(0 .. needle.len()).all(|j| chars[i + j].c == needle[j]) {
+            i += 1;
+            continue
+        }
+        let m = &chars[i .. i + needle.len()];
+        let mut boxes: Vec<BBox> = vec![];
+        let mut from = 0;
+        for j in 1 ..= m.len() {
+            if j == m.len() || m[j].line != m[from].line {
+                boxes.push(merge_boxes(m[from .. j].iter().map(|ch| &ch.b_box)));
+                from = j;
+            }
+        }
+        r.push(boxes);
+        i += needle.len();
+    }
+    r
+}
+
+pub fn page_lines(doc: &poppler::PopplerDocument, num: usize) -> Result<Vec<TT>,glib::error::Error> {
+    let page = doc.get_page(num)?;
+    let text = page.get_text();
+    if text.is_empty() {
+        return Ok(vec![])
+    }
+    let layout = page.get_text_layout()?;
+    let attr = page.get_text_attributes();
+    validate_page(&text, &attr, &layout)?;
+
+    let mut from = 0;
+    let mut from_l = 0;
+    let mut char_cnt = 0;
+    let mut strings = vec![];
+
+    for (i,c) in text.char_indices() {
+        if c == '\n' {
+            let f = font(&attr, from_l, char_cnt);
+            let tt = TT {
+                text: String::from(&text[from..i]),
+                b_box: b_box(&layout[from_l..char_cnt+1]),
+                font_name: font_name(f),
+                font_size: f.font_size
+            };
+            strings.push(tt);
+            from = i + 1;
+            from_l = char_cnt + 1;
+        }
+        char_cnt += 1;
+    }
+    // the last line need not end with '\n'
+    if from_l < char_cnt {
+        let f = font(&attr, from_l, char_cnt - 1);
+        strings.push(TT {
+            text: String::from(&text[from..]),
+            b_box: b_box(&layout[from_l..char_cnt]),
+            font_name: font_name(f),
+            font_size: f.font_size
+        });
+    }
+    Ok(strings)
+}
+
+pub fn document_lines(doc: &poppler::PopplerDocument) -> Result<Vec<Vec<TT>>,glib::error::Error> {
+    let mut r = vec![];
+    for i in 0 .. doc.get_n_pages() {
+        r.push(page_lines(doc, i)?);
+    }
+    Ok(r)
+}
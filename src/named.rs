@@ -21,7 +21,7 @@ fn get_authors(s: &str) -> Vec<item::Author> {
     result
 }
 
-fn get_publisher(_s: &str) -> (Option<String>,Option<u32>) {
+fn get_publisher(_s: &str) -> (Option<String>,Option<item::Date>) {
     (None, None)
 }
 
@@ -36,7 +36,14 @@ fn get_item(s: &str) -> Option<item::Item>{
         authors: get_authors(v[0]),
         publisher: p,
         pub_date: d,
-        global_id: None
+        journal: None,
+        history: item::History::default(),
+        abstract_text: None,
+        categories: vec![],
+        disagreements: vec![],
+        ids: vec![],
+        primary: None,
+        thesis: None
     })
 }
 
@@ -0,0 +1,149 @@
+// Clean-up of extracted text: ligatures expanded, soft hyphens dropped, NFC, plain quotes and
+// dashes, and words hyphenated at line ends rejoined. Whether the hyphen stays ("well-known")
+// is decided from an optional word list, then from how the document spells the word elsewhere.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+
+use unicode_normalization::UnicodeNormalization;
+
+const SOFT_HYPHEN: char = '\u{ad}';
+
+pub struct Normalizer {
+    // leave the text as extracted
+    pub raw: bool,
+    words: HashSet<String>,
+    // document vocabulary, see learn
+    seen: HashSet<String>,
+}
+
+fn ligature(c: char) -> Option<&'static str> {
+    match c {
+        '\u{fb00}' => Some("ff"),
+        '\u{fb01}' => Some("fi"),
+        '\u{fb02}' => Some("fl"),
+        '\u{fb03}' => Some("ffi"),
+        '\u{fb04}' => Some("ffl"),
+        '\u{fb05}' | '\u{fb06}' => Some("st"),
+        _ => None
+    }
+}
+
+fn plain(c: char) -> char {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' => '\'',
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' => '"',
+        '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2212}' => '-',
+        '\u{2015}' => '\u{2014}',
+        '\u{a0}' => ' ',
+        _ => c
+    }
+}
+
+// a line's characters without the line-end hyphenation logic
+pub fn normalize_line(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    for c in s.nfc() {
+        match ligature(c) {
+            Some(x) => r.push_str(x),
+            None if c == SOFT_HYPHEN => (),
+            None => r.push(plain(c))
+        }
+    }
+    r
+}
+
+fn key(s: &str) -> String {
+    s.trim_matches(|c: char| !c.is_alphanumeric() && c != '-').to_lowercase()
+}
+
+impl Normalizer {
+    pub fn new() -> Normalizer {
+        Normalizer{raw: false, words: HashSet::new(), seen: HashSet::new()}
+    }
+
+    // one word per line; '#' starts a comment
+    pub fn load_words(&mut self, path: &str) -> io::Result<()> {
+        for l in fs::read_to_string(path)?.lines() {
+            let w = l.split('#').next().unwrap_or("").trim();
+            if !w.is_empty() {
+                self.words.insert(w.to_lowercase());
+            }
+        }
+        Ok(())
+    }
+
+    // words of the document that are not split by a line end
+    pub fn learn(&mut self, text: &str) {
+        for l in text.lines() {
+            let l = normalize_line(l);
+            let ws = l.split_whitespace().collect::<Vec<_>>();
+            for (i, w) in ws.iter().enumerate() {
+                if i == 0 || (i + 1 == ws.len() && w.ends_with('-')) {
+                    continue
+                }
+                let k = key(w);
+                if !k.is_empty() {
+                    self.seen.insert(k);
+                }
+            }
+        }
+    }
+
+    fn keep_hyphen(&self, head: &str, tail: &str) -> bool {
+        let (h, t) = (key(head), key(tail));
+        let joined = format!("{}{}", h, t);
+        let compound = format!("{}-{}", h, t);
+        if self.words.contains(&joined) {
+            return false
+        }
+        if self.words.contains(&compound) || (self.words.contains(&h) && self.words.contains(&t)) {
+            return true
+        }
+        if self.seen.contains(&joined) {
+            return false
+        }
+        if self.seen.contains(&compound) {
+            return true
+        }
+        // a hyphen already inside the word ("state-of-the-") or numbers and names around it
+        h.contains('-') || head.chars().last().map_or(false, |c| c.is_ascii_digit())
+            || tail.chars().next().map_or(true, |c| !c.is_lowercase())
+    }
+
+    // a single line or the lines of a block joined by '\n'; lines are kept, a word broken at
+    // the end of one line is moved up whole
+    pub fn text(&self, s: &str) -> String {
+        if self.raw {
+            return String::from(s)
+        }
+        let mut lines = s.split('\n').map(|l| {
+            // a trailing soft hyphen always marks a break inside a word
+            let soft = l.trim_end().ends_with(SOFT_HYPHEN);
+            let mut l = normalize_line(l);
+            if soft {
+                l = String::from(l.trim_end());
+                l.push(SOFT_HYPHEN);
+            }
+            l
+        }).collect::<Vec<_>>();
+        for i in 0 .. lines.len().saturating_sub(1) {
+            let cur = lines[i].trim_end().to_string();
+            let soft = cur.ends_with(SOFT_HYPHEN);
+            let hyphen = soft || (cur.ends_with('-') && cur.chars().rev().nth(1).map_or(false, |c| c.is_alphabetic()));
+            let next = lines[i + 1].trim_start().to_string();
+            let tail = match next.split_whitespace().next() {
+                Some(x) if hyphen && x.chars().next().map_or(false, |c| c.is_alphabetic()) => String::from(x),
+                _ => continue
+            };
+            let mut head = cur;
+            head.pop();
+            let first = head.split_whitespace().last().unwrap_or("");
+            let word = if !soft && self.keep_hyphen(first, &tail) { format!("{}-{}", head, tail) } else { format!("{}{}", head, tail) };
+            lines[i] = word;
+            lines[i + 1] = next[tail.len() ..].trim_start().to_string();
+        }
+        lines.iter().map(|l| l.trim_end_matches(SOFT_HYPHEN)).collect::<Vec<_>>().join("\n")
+    }
+}
@@ -0,0 +1,186 @@
+// Local mirror of arXiv OAI-PMH metadata, arXivRaw or oai_dc ListRecords responses
+
+use std::collections::HashMap;
+use std::io;
+
+use xml::reader::{EventReader, XmlEvent};
+
+use crate::item::{split_version, Author, GlobalId, IdSource, Item, JournalRef};
+use crate::journal;
+use crate::thesis::find_year;
+
+#[derive(Debug,Default,Clone)]
+pub struct ArxivRecord {
+    pub id: String,
+    pub title: String,
+    pub authors: Vec<Author>,
+    pub abstract_text: String,
+    pub categories: Vec<String>,
+    pub journal_ref: Option<String>,
+    pub doi: Option<String>,
+    pub versions: u32,
+}
+
+pub struct ArxivIndex {
+    records: HashMap<String, ArxivRecord>,
+}
+
+fn squash(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// "C. Balázs, E. L. Berger and C.-P. Yuan"
+fn raw_authors(s: &str) -> Vec<Author> {
+    squash(s).replace(" and ", ", ")
+        .split(", ")
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(Author::from_name)
+        .collect()
+}
+
+fn parse<R: io::Read>(src: R, records: &mut HashMap<String, ArxivRecord>) -> io::Result<()> {
+    let mut rec = ArxivRecord::default();
+    let mut text = String::new();
+    let mut dates = 0;
+    for e in EventReader::new(src) {
+        match e {
+            Ok(XmlEvent::StartElement{name, attributes, ..}) => {
+                text.clear();
+                if name.local_name == "record" {
+                    rec = ArxivRecord::default();
+                    dates = 0;
+                }
+                if name.local_name == "version" {
+                    for a in attributes {
+                        if a.name.local_name == "version" {
+                            let v = a.value.trim_start_matches('v').parse::<u32>().unwrap_or(0);
+                            if v > rec.versions {
+                                rec.versions = v;
+                            }
+                        }
+                    }
+                }
+            },
+            Ok(XmlEvent::Characters(s)) | Ok(XmlEvent::CData(s)) => text.push_str(&s),
+            Ok(XmlEvent::EndElement{name}) => {
+                let t = squash(&text);
+                match (name.prefix.as_ref().map(|x| x.as_str()), name.local_name.as_str()) {
+                    // header
+                    (_, "identifier") if t.starts_with("oai:arXiv.org:") => {
+                        rec.id = String::from(&t["oai:arXiv.org:".len()..]);
+                    },
+                    // arXivRaw
+                    (None, "id") => rec.id = t,
+                    (None, "title") => rec.title = t,
+                    (None, "authors") => rec.authors = raw_authors(&t),
+                    (None, "abstract") => rec.abstract_text = t,
+                    (None, "categories") => rec.categories = t.split_whitespace().map(String::from).collect(),
+                    (None, "journal-ref") => rec.journal_ref = Some(t),
+                    (None, "doi") => rec.doi = Some(t),
+                    // oai_dc
+                    (Some("dc"), "title") => rec.title = t,
+                    (Some("dc"), "creator") => rec.authors.push(Author::from_name(&t)),
+                    (Some("dc"), "subject") => rec.categories.push(t),
+                    (Some("dc"), "description") if rec.abstract_text.is_empty() => rec.abstract_text = t,
+                    (Some("dc"), "date") => dates += 1,
+                    (Some("dc"), "identifier") => {
+                        if t.starts_with("doi:") {
+                            rec.doi = Some(String::from(&t[4..]));
+                        }
+                        else if !t.starts_with("http") && rec.journal_ref.is_none() {
+                            rec.journal_ref = Some(t);
+                        }
+                    },
+                    (_, "record") => {
+                        if rec.versions == 0 {
+                            rec.versions = dates;
+                        }
+                        if !rec.id.is_empty() {
+                            let r = std::mem::replace(&mut rec, ArxivRecord::default());
+                            records.insert(String::from(split_version(&r.id).0), r);
+                        }
+                    },
+                    _ => ()
+                }
+                text.clear();
+            },
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            _ => ()
+        }
+    }
+    Ok(())
+}
+
+impl ArxivIndex {
+    // a single XML file or a directory of harvested responses
+    pub fn load(path: &str) -> io::Result<ArxivIndex> {
+        let mut records = HashMap::new();
+        let meta = std::fs::metadata(path)?;
+        if meta.is_dir() {
+            for entry in walkdir::WalkDir::new(path) {
+                let entry = entry.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                let is_xml = entry.path().extension().and_then(|x| x.to_str()) == Some("xml");
+                if entry.file_type().is_file() && is_xml {
+                    parse(io::BufReader::new(std::fs::File::open(entry.path())?), &mut records)?;
+                }
+            }
+        }
+        else {
+            parse(io::BufReader::new(std::fs::File::open(path)?), &mut records)?;
+        }
+        Ok(ArxivIndex{records})
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ArxivRecord> {
+        self.records.get(split_version(id).0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ArxivMatch {
+    pub id: String,
+    pub local_version: Option<u32>,
+    pub latest_version: u32,
+}
+
+impl ArxivMatch {
+    pub fn outdated(&self) -> bool {
+        match self.local_version {
+            Some(v) => v < self.latest_version,
+            None => false
+        }
+    }
+}
+
+// the dump is authoritative for title, authors, abstract, categories and journal-ref
+pub fn enrich(item: &mut Item, index: &ArxivIndex) -> Option<ArxivMatch> {
+    let id = item.own_ids().filter_map(|x| match *x {
+        GlobalId::ARXIV(ref a) => Some(a.clone()),
+        _ => None
+    }).next()?;
+    let rec = index.get(&id)?;
+    if !rec.title.is_empty() {
+        item.title = rec.title.clone();
+    }
+    if !rec.authors.is_empty() {
+        item.authors = rec.authors.clone();
+    }
+    if !rec.abstract_text.is_empty() {
+        item.abstract_text = Some(rec.abstract_text.clone());
+    }
+    item.categories = rec.categories.clone();
+    if let Some(ref j) = rec.journal_ref {
+        // "Phys.Rev.D76:013009,2007" rarely parses, keep it whole
+        item.journal = Some(journal::journal_line(j).unwrap_or(JournalRef{
+            journal: j.clone(), year: find_year(j), ..JournalRef::default()}));
+    }
+    if let Some(ref d) = rec.doi {
+        item.add_id(GlobalId::DOI(d.clone()), IdSource::Lookup);
+    }
+    Some(ArxivMatch{id: String::from(split_version(&id).0), local_version: split_version(&id).1, latest_version: rec.versions})
+}
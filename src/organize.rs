@@ -0,0 +1,228 @@
+// Library tree from a layout template; every change is journalled so a run can be undone
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::item::{self, Item, ItemType};
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Mode {
+    Move,
+    Copy,
+    Hardlink,
+    Symlink,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Conflict {
+    Skip,
+    Rename,
+    Overwrite,
+}
+
+impl Mode {
+    pub fn from_str(s: &str) -> Option<Mode> {
+        match s {
+            "move" => Some(Mode::Move),
+            "copy" => Some(Mode::Copy),
+            "hardlink" => Some(Mode::Hardlink),
+            "symlink" => Some(Mode::Symlink),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            Mode::Move => "move",
+            Mode::Copy => "copy",
+            Mode::Hardlink => "hardlink",
+            Mode::Symlink => "symlink"
+        }
+    }
+}
+
+impl Conflict {
+    pub fn from_str(s: &str) -> Option<Conflict> {
+        match s {
+            "skip" => Some(Conflict::Skip),
+            "rename" => Some(Conflict::Rename),
+            "overwrite" => Some(Conflict::Overwrite),
+            _ => None
+        }
+    }
+}
+
+// one path component: no separators, no leading dots
+fn component(s: &str) -> String {
+    let s = s.replace('/', "-").replace('\\', "-");
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    let s = s.trim_start_matches('.');
+    let s = s.chars().take(80).collect::<String>();
+    if s.is_empty() { String::from("unknown") } else { s }
+}
+
+// {type} {year} {first_author} {authors} {title} {journal} {publisher}
+pub fn expand(layout: &str, t: &ItemType, it: &Item) -> PathBuf {
+    let year = it.pub_date.map(|x| x.year)
+        .or_else(|| it.journal.as_ref().and_then(|x| x.year))
+        .map(|x| x.to_string())
+        .unwrap_or_default();
+    let first_author = it.authors.first().map(|x| x.last_name.clone()).unwrap_or_default();
+    let authors = it.authors.iter().map(|x| x.last_name.clone()).collect::<Vec<_>>().join(", ");
+    let journal = it.journal.as_ref().map(|x| x.journal.clone()).unwrap_or_default();
+    let publisher = it.publisher.clone().unwrap_or_default();
+    let mut r = PathBuf::new();
+    for part in layout.split('/').filter(|x| !x.is_empty()) {
+        let v = part
+            .replace("{type}", item::type_name(t))
+            .replace("{year}", &year)
+            .replace("{first_author}", &first_author)
+            .replace("{authors}", &authors)
+            .replace("{title}", &it.title)
+            .replace("{journal}", &journal)
+            .replace("{publisher}", &publisher);
+        r.push(component(&v));
+    }
+    r
+}
+
+#[derive(Debug)]
+pub struct Action {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    // an existing file at `to` is set aside here first
+    pub backup: Option<PathBuf>,
+}
+
+// "a.pdf" -> "a (2).pdf"
+fn free_name(p: &Path) -> PathBuf {
+    let stem = p.file_stem().and_then(|x| x.to_str()).unwrap_or("");
+    let ext = p.extension().and_then(|x| x.to_str()).map_or(String::new(), |x| format!(".{}", x));
+    let mut n = 2;
+    loop {
+        let q = p.with_file_name(format!("{} ({}){}", stem, n, ext));
+        if !q.exists() {
+            return q
+        }
+        n += 1;
+    }
+}
+
+// None when the file is already in place or is skipped; `taken` holds the targets and backups
+// of the actions planned before
+pub fn plan(from: &Path, dest: &Path, dir: &Path, policy: Conflict, taken: &[PathBuf]) -> Option<Action> {
+    let to = dest.join(dir).join(from.file_name()?);
+    if to == from {
+        return None
+    }
+    let clash = to.exists() || taken.contains(&to);
+    if !clash {
+        return Some(Action{from: from.to_path_buf(), to, backup: None})
+    }
+    match policy {
+        Conflict::Skip => None,
+        Conflict::Rename => {
+            let mut q = free_name(&to);
+            while taken.contains(&q) {
+                q = free_name(&q);
+            }
+            Some(Action{from: from.to_path_buf(), to: q, backup: None})
+        },
+        Conflict::Overwrite => {
+            // an earlier backup of the same file is kept too
+            let mut bak = PathBuf::from(format!("{}.ginger-bak", to.display()));
+            while bak.exists() || taken.contains(&bak) {
+                bak = free_name(&bak);
+            }
+            Some(Action{from: from.to_path_buf(), to, backup: Some(bak)})
+        }
+    }
+}
+
+fn absolute(p: &Path) -> io::Result<PathBuf> {
+    std::fs::canonicalize(p)
+}
+
+// rename, falling back to copy and remove across file systems
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(dir) = to.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+// like `absolute`, for paths that no longer or not yet exist
+fn from_cwd(p: &Path) -> io::Result<PathBuf> {
+    if p.is_absolute() {
+        Ok(p.to_path_buf())
+    }
+    else {
+        Ok(std::env::current_dir()?.join(p))
+    }
+}
+
+// one JSON object per line, so any path survives: {"op": "backup", "from": PATH, "to": BAK},
+// then {"op": MODE, "from": FROM, "to": TO}; paths are absolute, so --undo works from any
+// directory
+fn journal_line(journal: &mut std::fs::File, op: &str, from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (from_cwd(from)?, from_cwd(to)?);
+    writeln!(journal, "{}", json!({"op": op, "from": from.to_string_lossy(), "to": to.to_string_lossy()}))
+}
+
+pub fn apply(a: &Action, mode: Mode, journal: &mut std::fs::File) -> io::Result<()> {
+    if let Some(dir) = a.to.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if let Some(ref bak) = a.backup {
+        if a.to.exists() {
+            std::fs::rename(&a.to, bak)?;
+            journal_line(journal, "backup", &a.to, bak)?;
+        }
+    }
+    match mode {
+        Mode::Move => move_file(&a.from, &a.to)?,
+        Mode::Copy => { std::fs::copy(&a.from, &a.to)?; },
+        Mode::Hardlink => std::fs::hard_link(&a.from, &a.to)?,
+        Mode::Symlink => std::os::unix::fs::symlink(absolute(&a.from)?, &a.to)?
+    }
+    journal_line(journal, mode.name(), &a.from, &a.to)?;
+    journal.flush()
+}
+
+pub fn open_journal(path: &Path) -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+// replays the journal backwards; returns the lines that could not be undone
+pub fn undo(path: &Path) -> io::Result<Vec<String>> {
+    let f = io::BufReader::new(std::fs::File::open(path)?);
+    let lines = f.lines().collect::<io::Result<Vec<_>>>()?;
+    let mut failed = vec![];
+    for line in lines.iter().rev() {
+        let v: Value = match serde_json::from_str(line) {
+            Ok(x) => x,
+            Err(_) => continue
+        };
+        let (op, a, b) = match (v["op"].as_str(), v["from"].as_str(), v["to"].as_str()) {
+            (Some(op), Some(a), Some(b)) => (op, Path::new(a), Path::new(b)),
+            _ => continue
+        };
+        let r = match op {
+            "move" => move_file(b, a),
+            "copy" | "hardlink" | "symlink" => std::fs::remove_file(b),
+            "backup" => std::fs::rename(b, a),
+            _ => Ok(())
+        };
+        if let Err(e) = r {
+            failed.push(format!("{}: {}", line, e));
+        }
+    }
+    std::fs::rename(path, format!("{}.undone", path.display()))?;
+    Ok(failed)
+}
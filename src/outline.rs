@@ -0,0 +1,251 @@
+// Section headings from numbering and fonts, merged with the PDF's own bookmarks
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::graph::title_similarity;
+use crate::layout::{font_key, FontKey, TT};
+use crate::poppler_ext::Bookmark;
+use crate::reading_order::reading_order;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Source {
+    Detected,
+    Outline,
+    Both,
+}
+
+#[derive(Debug,Clone)]
+pub struct Heading {
+    pub title: String,
+    pub number: Option<String>,
+    pub level: usize,
+    pub page: Option<usize>,
+    pub source: Source,
+}
+
+#[derive(Debug,Clone)]
+pub struct Section {
+    pub heading: Heading,
+    pub children: Vec<Section>,
+}
+
+// characters set in each font, most used first
+pub fn font_histogram(pages: &[Vec<TT>]) -> Vec<(FontKey, usize)> {
+    let mut h: HashMap<FontKey, usize> = HashMap::new();
+    for t in pages.iter().flat_map(|p| p.iter()) {
+        *h.entry(font_key(t)).or_insert(0) += t.text.chars().filter(|c| !c.is_whitespace()).count();
+    }
+    let mut r = h.into_iter().collect::<Vec<_>>();
+    r.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    r
+}
+
+pub fn is_bold(font: &str) -> bool {
+    let f = font.to_lowercase();
+    ["bold", "black", "heavy", "demi", "cmbx", "cmssbx"].iter().any(|x| f.contains(x))
+}
+
+fn is_roman(s: &str) -> bool {
+    !s.is_empty() && s.len() <= 5 && s.chars().all(|c| "IVXL".contains(c))
+}
+
+// "3.2 Main results" -> ("3.2", 2, "Main results"); also "IV. RESULTS", "A.1 Proofs", "Appendix B Tables"
+pub fn numbering(s: &str) -> Option<(String, usize, &str)> {
+    let s = s.trim();
+    let (s, appendix) = if s.starts_with("Appendix ") { (s["Appendix ".len()..].trim_start(), true) } else { (s, false) };
+    let end = s.find(char::is_whitespace)?;
+    let num = s[..end].trim_end_matches('.');
+    let rest = s[end..].trim_start();
+    if num.is_empty() || !rest.chars().next().map_or(false, |c| c.is_uppercase()) {
+        return None
+    }
+    let parts = num.split('.').collect::<Vec<_>>();
+    let digits = |x: &str| !x.is_empty() && x.len() <= 2 && x.chars().all(|c| c.is_ascii_digit());
+    let first = parts[0];
+    let ok = if digits(first) {
+        first != "0" && parts[1..].iter().all(|x| digits(x))
+    }
+    else if is_roman(first) && parts.len() == 1 {
+        true
+    }
+    else {
+        first.len() == 1 && first.chars().all(|c| c.is_ascii_uppercase())
+            && (parts.len() > 1 || appendix) && parts[1..].iter().all(|x| digits(x))
+    };
+    if ok {
+        Some((String::from(num), parts.len(), rest))
+    }
+    else {
+        None
+    }
+}
+
+fn words(s: &str) -> usize {
+    s.split_whitespace().count()
+}
+
+// numbered or set in a font larger or bolder than the body text
+pub fn headings(pages: &[Vec<TT>]) -> Vec<Heading> {
+    let hist = font_histogram(pages);
+    let body = match hist.first() {
+        Some(x) => x.0.clone(),
+        None => return vec![]
+    };
+    let body_size = body.1 as f64 / 10.0;
+    let heading_font = |t: &TT| {
+        t.font_size > body_size + 0.5 || (is_bold(&t.font_name) && !is_bold(&body.0) && t.font_size >= body_size - 0.5)
+    };
+    // the title is the largest text on the first page
+    let title_size = pages.first().map_or(0.0, |p| p.iter().map(|x| x.font_size).fold(0.0, f64::max));
+    let mut found: Vec<(Heading, FontKey, f64)> = vec![];
+    for (pi, p) in pages.iter().enumerate() {
+        let mut last: Option<usize> = None;
+        for i in reading_order(p) {
+            let t = &p[i];
+            let text = t.text.trim();
+            if text.is_empty() || text.chars().count() > 120 || words(text) > 15 || !text.chars().any(|c| c.is_alphabetic()) {
+                last = None;
+                continue
+            }
+            if pi == 0 && t.font_size >= title_size && title_size > body_size + 0.5 {
+                last = None;
+                continue
+            }
+            let hf = heading_font(t);
+            let num = numbering(text);
+            // a heading wrapped onto a second line
+            if let Some(k) = last {
+                let prev = &found[k];
+                if num.is_none() && hf && prev.1 == font_key(t) && t.b_box.top - prev.2 < 1.5 * t.font_size {
+                    let h = &mut found[k].0;
+                    h.title = format!("{} {}", h.title, text);
+                    found[k].2 = t.b_box.bottom;
+                    continue
+                }
+            }
+            let is_heading = match num {
+                Some(_) => hf || (words(text) <= 6 && !text.ends_with('.') && font_key(t) == body),
+                None => hf && text.chars().next().map_or(false, |c| c.is_uppercase()) && words(text) <= 12
+            };
+            if !is_heading || text.ends_with(',') || text.ends_with(';') {
+                last = None;
+                continue
+            }
+            let (number, level) = match num {
+                Some((n, depth, _)) => (Some(n), depth),
+                None => (None, 0)
+            };
+            found.push((Heading{title: String::from(text), number, level, page: Some(pi), source: Source::Detected},
+                        font_key(t), t.b_box.bottom));
+            last = Some(found.len() - 1);
+        }
+    }
+    // unnumbered headings take the level of numbered ones in the same font, or rank by size
+    let mut by_font: HashMap<FontKey, usize> = HashMap::new();
+    for (h, f, _) in &found {
+        if h.number.is_some() {
+            by_font.entry(f.clone()).or_insert(h.level);
+        }
+    }
+    let mut fonts = found.iter().map(|x| x.1.clone()).collect::<Vec<_>>();
+    fonts.sort_by(|a, b| b.1.cmp(&a.1).then(is_bold(&b.0).cmp(&is_bold(&a.0))));
+    fonts.dedup();
+    found.into_iter().map(|(mut h, f, _)| {
+        if h.level == 0 {
+            h.level = by_font.get(&f).cloned().unwrap_or_else(|| fonts.iter().position(|x| *x == f).unwrap_or(0) + 1);
+        }
+        h
+    }).collect()
+}
+
+fn flatten(b: &[Bookmark], level: usize, r: &mut Vec<Heading>) {
+    for x in b {
+        r.push(Heading {
+            title: x.title.trim().to_string(),
+            number: numbering(&x.title).map(|n| n.0),
+            level,
+            page: x.page,
+            source: Source::Outline,
+        });
+        flatten(&x.children, level + 1, r);
+    }
+}
+
+fn bare_title(h: &Heading) -> &str {
+    match numbering(&h.title) {
+        Some((_, _, rest)) => rest,
+        None => &h.title
+    }
+}
+
+fn same_heading(a: &Heading, b: &Heading) -> bool {
+    let near = match (a.page, b.page) {
+        (Some(x), Some(y)) => (x as i64 - y as i64).abs() <= 1,
+        _ => true
+    };
+    near && title_similarity(bare_title(a), bare_title(b)) >= 0.85
+}
+
+// the PDF outline is the skeleton; detected headings it lacks are slotted in by page
+pub fn merge(detected: Vec<Heading>, outline: &[Bookmark]) -> Vec<Heading> {
+    let mut r = vec![];
+    flatten(outline, 1, &mut r);
+    if r.is_empty() {
+        return detected
+    }
+    for h in detected {
+        if let Some(x) = r.iter_mut().find(|x| x.source == Source::Outline && same_heading(x, &h)) {
+            x.source = Source::Both;
+            if x.page.is_none() {
+                x.page = h.page;
+            }
+            continue
+        }
+        let page = h.page.unwrap_or(0);
+        let at = r.iter().rposition(|x| x.page.map_or(false, |p| p <= page)).map_or(0, |i| i + 1);
+        r.insert(at, h);
+    }
+    r
+}
+
+pub fn tree(hs: Vec<Heading>) -> Vec<Section> {
+    let mut root: Vec<Section> = vec![];
+    for h in hs {
+        // descend along the last sections while they are shallower
+        let mut list = &mut root;
+        loop {
+            let descend = match list.last() {
+                Some(s) => s.heading.level < h.level,
+                None => false
+            };
+            if !descend {
+                break
+            }
+            list = &mut list.last_mut().unwrap().children;
+        }
+        list.push(Section{heading: h, children: vec![]});
+    }
+    root
+}
+
+fn section_json(s: &Section) -> Value {
+    let source = match s.heading.source {
+        Source::Detected => "detected",
+        Source::Outline => "outline",
+        Source::Both => "both"
+    };
+    json!({
+        "title": s.heading.title,
+        "number": s.heading.number,
+        "level": s.heading.level,
+        "page": s.heading.page.map(|x| x + 1),
+        "source": source,
+        "children": s.children.iter().map(section_json).collect::<Vec<_>>(),
+    })
+}
+
+pub fn to_json(file: &str, sections: &[Section]) -> Value {
+    json!({"file": file, "sections": sections.iter().map(section_json).collect::<Vec<_>>()})
+}
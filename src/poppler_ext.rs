@@ -0,0 +1,436 @@
+// Parts of poppler-glib not covered by the poppler crate: document metadata, links, annotations,
+// the outline, and rendering page areas to PNG through Cairo.
+// The document is opened a second time through the C API.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::ptr;
+
+use glib_sys::{GArray, GError, GList, g_array_unref, g_error_free, g_filename_to_uri, g_free, gpointer};
+
+#[repr(C)]
+struct PopplerRectangle {
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64
+}
+
+// PopplerAction is a union, every member starts with the action type
+#[repr(C)]
+struct PopplerActionUri {
+    action_type: c_int,
+    title: *mut c_char,
+    uri: *mut c_char
+}
+
+#[repr(C)]
+struct PopplerLinkMapping {
+    area: PopplerRectangle,
+    action: *mut PopplerActionUri
+}
+
+const POPPLER_ACTION_GOTO_DEST: c_int = 2;
+const POPPLER_ACTION_URI: c_int = 5;
+
+#[repr(C)]
+struct PopplerActionGotoDest {
+    action_type: c_int,
+    title: *mut c_char,
+    dest: *mut PopplerDest
+}
+
+// leading fields only, the struct is always read through a pointer
+#[repr(C)]
+struct PopplerDest {
+    dest_type: c_int,
+    page_num: c_int,
+    left: f64,
+    bottom: f64,
+    right: f64,
+    top: f64,
+    zoom: f64,
+    named_dest: *mut c_char
+}
+
+const POPPLER_DEST_NAMED: c_int = 9;
+
+#[derive(Debug,Clone)]
+pub struct Bookmark {
+    pub title: String,
+    // from 0
+    pub page: Option<usize>,
+    pub children: Vec<Bookmark>
+}
+
+#[repr(C)]
+struct PopplerAnnotMapping {
+    area: PopplerRectangle,
+    annot: *mut c_void
+}
+
+#[repr(C)]
+#[derive(Clone,Copy)]
+struct PopplerPoint {
+    x: f64,
+    y: f64
+}
+
+#[repr(C)]
+struct PopplerQuadrilateral {
+    p1: PopplerPoint,
+    p2: PopplerPoint,
+    p3: PopplerPoint,
+    p4: PopplerPoint
+}
+
+// PopplerAnnotType
+static ANNOT_TYPES: [&str; 18] = ["unknown", "text", "link", "free-text", "line", "square", "circle",
+    "polygon", "poly-line", "highlight", "underline", "squiggly", "strike-out", "stamp", "caret",
+    "ink", "popup", "file-attachment"];
+
+// types poppler-glib wraps as PopplerAnnotMarkup
+fn is_markup(t: c_int) -> bool {
+    t == 1 || (t >= 3 && t <= 6) || (t >= 9 && t <= 12) || t == 17
+}
+
+// highlight, underline, squiggly, strike-out
+fn is_text_markup(t: c_int) -> bool {
+    t >= 9 && t <= 12
+}
+
+// rectangles here use the top-left origin of get_text_layout
+#[derive(Debug,Clone)]
+pub struct Rect {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64
+}
+
+#[derive(Debug,Clone)]
+pub struct Link {
+    pub uri: String,
+    pub area: Rect
+}
+
+#[derive(Debug,Clone)]
+pub struct Annot {
+    pub kind: &'static str,
+    pub contents: Option<String>,
+    pub author: Option<String>,
+    // raw PDF date, "D:20190412103000+02'00'"
+    pub modified: Option<String>,
+    pub area: Rect,
+    // marked text regions of highlights and the like
+    pub quads: Vec<Rect>
+}
+
+#[link(name = "poppler-glib")]
+extern "C" {
+    fn poppler_document_new_from_file(uri: *const c_char, password: *const c_char, error: *mut *mut GError) -> *mut c_void;
+    fn poppler_document_get_n_pages(document: *mut c_void) -> c_int;
+    fn poppler_document_get_page(document: *mut c_void, index: c_int) -> *mut c_void;
+    fn poppler_document_get_metadata(document: *mut c_void) -> *mut c_char;
+    fn poppler_page_get_link_mapping(page: *mut c_void) -> *mut GList;
+    fn poppler_page_free_link_mapping(list: *mut GList);
+    fn poppler_page_get_size(page: *mut c_void, width: *mut f64, height: *mut f64);
+    fn poppler_page_get_annot_mapping(page: *mut c_void) -> *mut GList;
+    fn poppler_page_free_annot_mapping(list: *mut GList);
+    fn poppler_annot_get_annot_type(annot: *mut c_void) -> c_int;
+    fn poppler_annot_get_contents(annot: *mut c_void) -> *mut c_char;
+    fn poppler_annot_get_modified(annot: *mut c_void) -> *mut c_char;
+    fn poppler_annot_markup_get_label(annot: *mut c_void) -> *mut c_char;
+    fn poppler_annot_text_markup_get_quadrilaterals(annot: *mut c_void) -> *mut GArray;
+    fn poppler_index_iter_new(document: *mut c_void) -> *mut c_void;
+    fn poppler_index_iter_get_child(parent: *mut c_void) -> *mut c_void;
+    fn poppler_index_iter_next(iter: *mut c_void) -> c_int;
+    fn poppler_index_iter_get_action(iter: *mut c_void) -> *mut PopplerActionGotoDest;
+    fn poppler_index_iter_free(iter: *mut c_void);
+    fn poppler_action_free(action: *mut PopplerActionGotoDest);
+    fn poppler_document_find_dest(document: *mut c_void, link_name: *const c_char) -> *mut PopplerDest;
+    fn poppler_dest_free(dest: *mut PopplerDest);
+    fn poppler_page_render(page: *mut c_void, cairo: *mut c_void);
+}
+
+const CAIRO_FORMAT_ARGB32: c_int = 0;
+
+#[link(name = "cairo")]
+extern "C" {
+    fn cairo_image_surface_create(format: c_int, width: c_int, height: c_int) -> *mut c_void;
+    fn cairo_surface_status(surface: *mut c_void) -> c_int;
+    fn cairo_surface_write_to_png(surface: *mut c_void, filename: *const c_char) -> c_int;
+    fn cairo_surface_destroy(surface: *mut c_void);
+    fn cairo_create(surface: *mut c_void) -> *mut c_void;
+    fn cairo_destroy(cr: *mut c_void);
+    fn cairo_scale(cr: *mut c_void, sx: f64, sy: f64);
+    fn cairo_translate(cr: *mut c_void, tx: f64, ty: f64);
+    fn cairo_set_source_rgb(cr: *mut c_void, red: f64, green: f64, blue: f64);
+    fn cairo_paint(cr: *mut c_void);
+}
+
+#[link(name = "gobject-2.0")]
+extern "C" {
+    fn g_object_unref(object: *mut c_void);
+}
+
+fn failed(msg: &str) -> glib::error::Error {
+    glib::error::Error::new(glib::FileError::Failed, &format!("XXX-ginger: {}", msg))
+}
+
+unsafe fn take_error(e: *mut GError) -> glib::error::Error {
+    if e.is_null() {
+        return failed("unknown poppler error")
+    }
+    let msg = CStr::from_ptr((*e).message).to_string_lossy().into_owned();
+    g_error_free(e);
+    failed(&msg)
+}
+
+unsafe fn take_string(p: *mut c_char) -> Option<String> {
+    if p.is_null() {
+        return None
+    }
+    let s = CStr::from_ptr(p).to_string_lossy().into_owned();
+    g_free(p as gpointer);
+    Some(s)
+}
+
+pub struct Document(*mut c_void);
+
+impl Document {
+    pub fn new_from_file(file: &str) -> Result<Document, glib::error::Error> {
+        let path = std::fs::canonicalize(file).map_err(|e| failed(&e.to_string()))?;
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| failed(&e.to_string()))?;
+        unsafe {
+            let mut err = ptr::null_mut();
+            let uri = g_filename_to_uri(path.as_ptr(), ptr::null(), &mut err);
+            if uri.is_null() {
+                return Err(take_error(err))
+            }
+            let doc = poppler_document_new_from_file(uri, ptr::null(), &mut err);
+            g_free(uri as gpointer);
+            if doc.is_null() {
+                return Err(take_error(err))
+            }
+            Ok(Document(doc))
+        }
+    }
+
+    pub fn get_n_pages(&self) -> usize {
+        unsafe { poppler_document_get_n_pages(self.0) as usize }
+    }
+
+    // XMP packet
+    pub fn get_metadata(&self) -> Option<String> {
+        unsafe { take_string(poppler_document_get_metadata(self.0)) }
+    }
+
+    // the URI links on a page with their areas
+    pub fn get_links(&self, num: usize) -> Vec<Link> {
+        let mut r = vec![];
+        if num >= self.get_n_pages() {
+            return r
+        }
+        unsafe {
+            let page = poppler_document_get_page(self.0, num as c_int);
+            if page.is_null() {
+                return r
+            }
+            let (mut width, mut height) = (0.0, 0.0);
+            poppler_page_get_size(page, &mut width, &mut height);
+            let list = poppler_page_get_link_mapping(page);
+            let mut l = list;
+            while !l.is_null() {
+                let m = (*l).data as *mut PopplerLinkMapping;
+                let a = (*m).action;
+                if !a.is_null() && (*a).action_type == POPPLER_ACTION_URI && !(*a).uri.is_null() {
+                    let area = &(*m).area;
+                    r.push(Link {
+                        uri: CStr::from_ptr((*a).uri).to_string_lossy().into_owned(),
+                        area: flip(area.x1, area.y1, area.x2, area.y2, height)
+                    });
+                }
+                l = (*l).next;
+            }
+            poppler_page_free_link_mapping(list);
+            g_object_unref(page);
+        }
+        r
+    }
+}
+
+// PDF space has its origin at the bottom left
+fn flip(x1: f64, y1: f64, x2: f64, y2: f64, height: f64) -> Rect {
+    Rect {
+        x1: x1.min(x2),
+        y1: height - y1.max(y2),
+        x2: x1.max(x2),
+        y2: height - y1.min(y2)
+    }
+}
+
+unsafe fn quads(annot: *mut c_void, height: f64) -> Vec<Rect> {
+    let a = poppler_annot_text_markup_get_quadrilaterals(annot);
+    if a.is_null() {
+        return vec![]
+    }
+    let q = std::slice::from_raw_parts((*a).data as *const PopplerQuadrilateral, (*a).len as usize);
+    let r = q.iter().map(|q| {
+        let p = [q.p1, q.p2, q.p3, q.p4];
+        let x1 = p.iter().map(|p| p.x).fold(std::f64::MAX, f64::min);
+        let x2 = p.iter().map(|p| p.x).fold(std::f64::MIN, f64::max);
+        let y1 = p.iter().map(|p| p.y).fold(std::f64::MAX, f64::min);
+        let y2 = p.iter().map(|p| p.y).fold(std::f64::MIN, f64::max);
+        flip(x1, y1, x2, y2, height)
+    }).collect();
+    g_array_unref(a);
+    r
+}
+
+impl Document {
+    // annotations of a page, without links, popups and form widgets
+    pub fn get_annots(&self, num: usize) -> Vec<Annot> {
+        let mut r = vec![];
+        if num >= self.get_n_pages() {
+            return r
+        }
+        unsafe {
+            let page = poppler_document_get_page(self.0, num as c_int);
+            if page.is_null() {
+                return r
+            }
+            let (mut width, mut height) = (0.0, 0.0);
+            poppler_page_get_size(page, &mut width, &mut height);
+            let list = poppler_page_get_annot_mapping(page);
+            let mut l = list;
+            while !l.is_null() {
+                let m = (*l).data as *mut PopplerAnnotMapping;
+                let annot = (*m).annot;
+                let t = poppler_annot_get_annot_type(annot);
+                if t != 2 && t != 16 && (t as usize) < ANNOT_TYPES.len() {
+                    let area = &(*m).area;
+                    r.push(Annot {
+                        kind: ANNOT_TYPES[t as usize],
+                        contents: take_string(poppler_annot_get_contents(annot)).filter(|x| !x.trim().is_empty()),
+                        author: if is_markup(t) { take_string(poppler_annot_markup_get_label(annot)) } else { None },
+                        modified: take_string(poppler_annot_get_modified(annot)),
+                        area: flip(area.x1, area.y1, area.x2, area.y2, height),
+                        quads: if is_text_markup(t) { quads(annot, height) } else { vec![] }
+                    });
+                }
+                l = (*l).next;
+            }
+            poppler_page_free_annot_mapping(list);
+            g_object_unref(page);
+        }
+        r
+    }
+}
+
+impl Document {
+    unsafe fn dest_page(&self, d: *mut PopplerDest) -> Option<usize> {
+        if d.is_null() {
+            return None
+        }
+        if (*d).dest_type != POPPLER_DEST_NAMED {
+            return if (*d).page_num > 0 { Some((*d).page_num as usize - 1) } else { None }
+        }
+        let named = poppler_document_find_dest(self.0, (*d).named_dest);
+        if named.is_null() {
+            return None
+        }
+        let r = if (*named).page_num > 0 { Some((*named).page_num as usize - 1) } else { None };
+        poppler_dest_free(named);
+        r
+    }
+
+    unsafe fn bookmarks(&self, iter: *mut c_void) -> Vec<Bookmark> {
+        let mut r = vec![];
+        loop {
+            let a = poppler_index_iter_get_action(iter);
+            if !a.is_null() {
+                let title = if (*a).title.is_null() {
+                    String::new()
+                }
+                else {
+                    CStr::from_ptr((*a).title).to_string_lossy().into_owned()
+                };
+                let page = if (*a).action_type == POPPLER_ACTION_GOTO_DEST { self.dest_page((*a).dest) } else { None };
+                poppler_action_free(a);
+                let child = poppler_index_iter_get_child(iter);
+                let children = if child.is_null() {
+                    vec![]
+                }
+                else {
+                    let c = self.bookmarks(child);
+                    poppler_index_iter_free(child);
+                    c
+                };
+                r.push(Bookmark{title, page, children});
+            }
+            if poppler_index_iter_next(iter) == 0 {
+                break
+            }
+        }
+        r
+    }
+
+    // the document outline (bookmarks), empty when there is none
+    pub fn get_outline(&self) -> Vec<Bookmark> {
+        unsafe {
+            let iter = poppler_index_iter_new(self.0);
+            if iter.is_null() {
+                return vec![]
+            }
+            let r = self.bookmarks(iter);
+            poppler_index_iter_free(iter);
+            r
+        }
+    }
+}
+
+impl Document {
+    // an area of a page (top-left origin, points) to a PNG file, `scale` pixels per point
+    pub fn render_png(&self, num: usize, area: &Rect, scale: f64, file: &str) -> Result<(), glib::error::Error> {
+        if num >= self.get_n_pages() {
+            return Err(failed("No such page"))
+        }
+        let name = CString::new(file).map_err(|e| failed(&e.to_string()))?;
+        let width = ((area.x2 - area.x1) * scale).ceil().max(1.0) as c_int;
+        let height = ((area.y2 - area.y1) * scale).ceil().max(1.0) as c_int;
+        unsafe {
+            let page = poppler_document_get_page(self.0, num as c_int);
+            if page.is_null() {
+                return Err(failed("No such page"))
+            }
+            let surface = cairo_image_surface_create(CAIRO_FORMAT_ARGB32, width, height);
+            if cairo_surface_status(surface) != 0 {
+                cairo_surface_destroy(surface);
+                g_object_unref(page);
+                return Err(failed("Cannot create image surface"))
+            }
+            let cr = cairo_create(surface);
+            cairo_set_source_rgb(cr, 1.0, 1.0, 1.0);
+            cairo_paint(cr);
+            cairo_scale(cr, scale, scale);
+            cairo_translate(cr, -area.x1, -area.y1);
+            poppler_page_render(page, cr);
+            cairo_destroy(cr);
+            let status = cairo_surface_write_to_png(surface, name.as_ptr());
+            cairo_surface_destroy(surface);
+            g_object_unref(page);
+            if status != 0 {
+                return Err(failed(&format!("Cannot write {}", file)))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Document {
+    fn drop(&mut self) {
+        unsafe { g_object_unref(self.0) }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::item::{Date, GlobalId};
+use crate::journal::parse_date;
+use crate::thesis::find_ci;
+
+#[derive(Debug,PartialEq)]
+pub enum Server {
+    BioRxiv,
+    MedRxiv,
+    Ssrn,
+}
+
+#[derive(Debug)]
+pub struct Preprint {
+    pub server: Server,
+    pub id: GlobalId,
+    pub posted: Option<String>,
+}
+
+impl Preprint {
+    pub fn date(&self) -> Option<Date> {
+        self.posted.as_ref().and_then(|x| parse_date(x))
+    }
+}
+
+fn until<'a>(s: &'a str, stop: &[char]) -> &'a str {
+    let end = s.find(|c: char| c.is_whitespace() || stop.contains(&c)).unwrap_or(s.len());
+    s[..end].trim_end_matches(|c| c == '.' || c == ',')
+}
+
+// "doi: https://doi.org/10.1101/2020.01.01.123456;" or "doi: http://dx.doi.org/10.1101/..."
+fn doi_after(s: &str) -> Option<String> {
+    let i = s.find("10.1101/")?;
+    let doi = until(&s[i..], &[';']);
+    if doi.len() > "10.1101/".len() {
+        Some(String::from(doi))
+    }
+    else {
+        None
+    }
+}
+
+// a date ends with its year, which follows the marker closely
+const DATE_LEN: usize = 40;
+
+// end of the first four-digit year in `s`
+fn year_end(s: &str) -> Option<usize> {
+    let b = s.as_bytes();
+    let mut i = 0;
+    while i < b.len() {
+        let n = b[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+        if n == 4 && s[i..i + 4].parse::<u32>().map_or(false, |y| y >= 1900 && y <= 2100) {
+            return Some(i + 4)
+        }
+        i += std::cmp::max(n, 1);
+    }
+    None
+}
+
+// the date up to the first year after `marker`
+fn date_after(s: &str, marker: &str) -> Option<String> {
+    let i = find_ci(s, marker)?;
+    let rest = s[i + marker.len()..].trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+    let rest = if rest.starts_with("online") { rest["online".len()..].trim_start() } else { rest };
+    let end = year_end(rest).filter(|&x| x <= DATE_LEN)?;
+    Some(rest[..end].split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+// "this version posted January 2, 2020." or "first posted online Jan. 2, 2020;"
+fn posted_after(s: &str) -> Option<String> {
+    date_after(s, "posted")
+}
+
+fn rxiv(text: &str, stamp: &str, server: Server) -> Option<Preprint> {
+    let i = find_ci(text, stamp)?;
+    let rest = &text[i..];
+    // the margin stamp is a single sentence, the DOI and date are within a few lines
+    let rest = &rest[..rest.char_indices().nth(400).map(|(j, _)| j).unwrap_or(rest.len())];
+    let doi = doi_after(rest)?;
+    Some(Preprint{server, id: GlobalId::DOI(doi), posted: posted_after(rest)})
+}
+
+pub fn biorxiv(text: &str) -> Option<Preprint> {
+    rxiv(text, "biorxiv preprint", Server::BioRxiv)
+}
+
+pub fn medrxiv(text: &str) -> Option<Preprint> {
+    rxiv(text, "medrxiv preprint", Server::MedRxiv)
+}
+
+// the number of the SSRN link `s` starts with: "ssrn.com/abstract=1234567" or
+// "ssrn.com/sol3/papers.cfm?abstract_id=1234567"
+fn ssrn_link(s: &str) -> Option<String> {
+    let j = s.find("abstract")?;
+    if s[..j].contains(char::is_whitespace) {
+        return None
+    }
+    let rest = s[j..].trim_start_matches(|c: char| c.is_alphabetic() || c == '_' || c == '=');
+    let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    if end == 0 {
+        return None
+    }
+    Some(String::from(&rest[..end]))
+}
+
+// every SSRN link in the text
+pub fn ssrn_ids(text: &str) -> Vec<GlobalId> {
+    let mut r = vec![];
+    let mut from = 0;
+    while let Some(i) = find_ci(&text[from..], "ssrn.com") {
+        from += i;
+        if let Some(x) = ssrn_link(&text[from..]) {
+            r.push(GlobalId::SSRN(x));
+        }
+        from += "ssrn.com".len();
+    }
+    r
+}
+
+// SSRN prints "Electronic copy available at: https://ssrn.com/abstract=1234567" at the foot of
+// each page, otherwise the link is looked for in `footer`, the top and bottom lines of the page;
+// links elsewhere usually go to cited papers. The date from "Posted: 12 Mar 2020" or the cover page's
+// "Date Written: March 1, 2020"
+pub fn ssrn(text: &str, footer: &str) -> Option<Preprint> {
+    let stamp = match find_ci(text, "electronic copy available at") {
+        Some(i) => {
+            let rest = &text[i..];
+            &rest[..rest.char_indices().nth(120).map(|(j, _)| j).unwrap_or(rest.len())]
+        },
+        None => footer
+    };
+    let id = ssrn_ids(stamp).into_iter().next()?;
+    let posted = posted_after(text).or_else(|| date_after(text, "date written"));
+    Some(Preprint{server: Server::Ssrn, id, posted})
+}
+
+pub fn preprint(text: &str, footer: &str) -> Option<Preprint> {
+    biorxiv(text).or_else(|| medrxiv(text)).or_else(|| ssrn(text, footer))
+}
@@ -0,0 +1,95 @@
+// Total reading order of the lines of a page. BBox::partial_cmp cannot order lines of different
+// columns, so column gutters are found from the horizontal coverage of the lines; lines crossing
+// a gutter (titles, wide figures) split the page into bands that are read column by column.
+
+use crate::layout::{median, TT};
+
+fn crosses(t: &TT, g: &(f64, f64)) -> bool {
+    t.b_box.left < g.0 && t.b_box.right > g.1
+}
+
+// vertical strips of whitespace between columns, left to right
+pub fn gutters(lines: &[TT]) -> Vec<(f64, f64)> {
+    let lines = lines.iter().filter(|x| !x.text.trim().is_empty() && x.b_box.length() > 0.0).collect::<Vec<_>>();
+    if lines.len() < 6 {
+        return vec![]
+    }
+    let min_x = lines.iter().map(|x| x.b_box.left).fold(std::f64::MAX, f64::min);
+    let max_x = lines.iter().map(|x| x.b_box.right).fold(std::f64::MIN, f64::max);
+    let n = (max_x - min_x).ceil() as usize + 1;
+    let mut cover = vec![0usize; n];
+    // full-width lines would hide the gutter
+    for t in lines.iter().filter(|x| x.b_box.length() < 0.6 * (max_x - min_x)) {
+        let a = (t.b_box.left - min_x).floor() as usize;
+        let b = std::cmp::min((t.b_box.right - min_x).ceil() as usize, n - 1);
+        for c in &mut cover[a ..= b] {
+            *c += 1;
+        }
+    }
+    let peak = cover.iter().cloned().max().unwrap_or(0);
+    let low = std::cmp::max(1, peak / 10);
+    let min_gap = median(lines.iter().map(|x| x.font_size).collect()).unwrap_or(0.0).max(6.0);
+    let mut r = vec![];
+    let mut i = 0;
+    while i < n {
+        if cover[i] > low {
+            i += 1;
+            continue
+        }
+        let start = i;
+        while i < n && cover[i] <= low {
+            i += 1;
+        }
+        // not the page margins
+        if start == 0 || i >= n || ((i - start) as f64) < min_gap {
+            continue
+        }
+        let g = (min_x + start as f64, min_x + i as f64);
+        let left = lines.iter().filter(|x| x.b_box.right <= g.0 + 1.0).count();
+        let right = lines.iter().filter(|x| x.b_box.left >= g.1 - 1.0).count();
+        // single-column body text runs straight through, e.g. past equation numbers
+        let across = lines.iter().filter(|x| crosses(x, &g)).count();
+        if left >= 3 && right >= 3 && across < std::cmp::min(left, right) {
+            r.push(g);
+        }
+    }
+    r
+}
+
+fn column(t: &TT, gutters: &[(f64, f64)]) -> usize {
+    let c = (t.b_box.left + t.b_box.right) / 2.0;
+    gutters.iter().filter(|g| (g.0 + g.1) / 2.0 < c).count()
+}
+
+fn by_position(lines: &[TT], a: usize, b: usize) -> std::cmp::Ordering {
+    let (x, y) = (&lines[a].b_box, &lines[b].b_box);
+    x.top.partial_cmp(&y.top).unwrap_or(std::cmp::Ordering::Equal)
+        .then(x.left.partial_cmp(&y.left).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+// indices of `lines` in reading order
+pub fn reading_order(lines: &[TT]) -> Vec<usize> {
+    let g = gutters(lines);
+    let mut idx = (0 .. lines.len()).collect::<Vec<_>>();
+    idx.sort_by(|&a, &b| by_position(lines, a, b));
+    if g.is_empty() {
+        return idx
+    }
+    let mut r = vec![];
+    let mut band: Vec<usize> = vec![];
+    let flush = |band: &mut Vec<usize>, r: &mut Vec<usize>| {
+        band.sort_by(|&a, &b| column(&lines[a], &g).cmp(&column(&lines[b], &g)).then(by_position(lines, a, b)));
+        r.extend(band.drain(..));
+    };
+    for i in idx {
+        if g.iter().any(|x| crosses(&lines[i], x)) {
+            flush(&mut band, &mut r);
+            r.push(i);
+        }
+        else {
+            band.push(i);
+        }
+    }
+    flush(&mut band, &mut r);
+    r
+}
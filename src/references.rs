@@ -0,0 +1,343 @@
+use crate::item::{Author, GlobalId};
+use crate::layout::TT;
+use crate::ident;
+use crate::thesis::{find_ci, find_year};
+
+#[derive(Debug,Clone,Default)]
+pub struct Citation {
+    pub label: Option<String>,
+    pub text: String,
+    pub authors: Vec<Author>,
+    pub title: Option<String>,
+    pub venue: Option<String>,
+    pub year: Option<u32>,
+    pub ids: Vec<GlobalId>,
+}
+
+static HEADINGS: [&str; 10] = [
+    "references", "bibliography", "literature cited", "works cited", "references and notes",
+    "cited literature", "literatur", "literaturverzeichnis", "références", "bibliographie",
+];
+
+// "References", "7. REFERENCES", small caps extracted as "R EFERENCES"
+fn is_heading(s: &str) -> bool {
+    let s = s.trim().trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ' ');
+    let s = s.to_lowercase().replace(" ", "");
+    HEADINGS.iter().any(|h| s == h.replace(" ", ""))
+}
+
+// the last reference heading in the second half of the document
+fn find_section(pages: &[Vec<TT>]) -> Option<(usize, usize)> {
+    let n = pages.len();
+    for p in (n / 2 .. n).rev() {
+        for l in (0 .. pages[p].len()).rev() {
+            if is_heading(&pages[p][l].text) {
+                return Some((p, l))
+            }
+        }
+    }
+    None
+}
+
+fn bracket_label(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if !s.starts_with('[') {
+        return None
+    }
+    let end = s.find(']')?;
+    if end > 12 {
+        return None
+    }
+    Some((&s[1..end], s[end+1..].trim_start()))
+}
+
+// "12. Author", "12 Author", "12) Author"
+fn number_label(s: &str) -> Option<(u32, &str)> {
+    let s = s.trim_start();
+    let n = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if n == 0 || n > 3 {
+        return None
+    }
+    let rest = &s[n..];
+    let rest = if rest.starts_with('.') || rest.starts_with(')') { &rest[1..] } else { rest };
+    if !rest.starts_with(' ') {
+        return None
+    }
+    let rest = rest.trim_start();
+    if !rest.chars().next().map_or(false, |c| c.is_alphabetic()) {
+        return None
+    }
+    Some((s[..n].parse::<u32>().unwrap(), rest))
+}
+
+enum Style {
+    Bracket,
+    Numbered,
+    Hanging,
+}
+
+fn style(lines: &[&TT]) -> Style {
+    let head = &lines[.. std::cmp::min(lines.len(), 20)];
+    if head.iter().filter(|x| bracket_label(&x.text).is_some()).count() >= 2 {
+        return Style::Bracket
+    }
+    let mut expected = 1;
+    for x in head {
+        if let Some((n, _)) = number_label(&x.text) {
+            if n == expected {
+                expected += 1;
+            }
+        }
+    }
+    if expected > 2 {
+        Style::Numbered
+    }
+    else {
+        Style::Hanging
+    }
+}
+
+fn join(acc: &mut String, line: &str) {
+    let line = line.trim();
+    if acc.ends_with('-') && line.chars().next().map_or(false, |c| c.is_lowercase()) {
+        acc.pop();
+    }
+    else if !acc.is_empty() {
+        acc.push(' ');
+    }
+    acc.push_str(line);
+}
+
+// first lines of author-year entries start at the column margin, continuation lines are indented
+fn hanging_starts(lines: &[&TT]) -> Vec<bool> {
+    let mut lefts = lines.iter().map(|x| x.b_box.left).collect::<Vec<_>>();
+    lefts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    // column margins: gaps of more than 50pt between line starts
+    let mut margins = vec![];
+    for (i, &x) in lefts.iter().enumerate() {
+        if i == 0 || x - lefts[i-1] > 50.0 {
+            margins.push(x);
+        }
+    }
+    lines.iter().map(|x| {
+        let m = margins.iter().filter(|&&m| m <= x.b_box.left + 0.01).last().cloned().unwrap_or(x.b_box.left);
+        x.b_box.left - m < 0.3 * x.font_size
+    }).collect()
+}
+
+fn segment(lines: &[&TT]) -> Vec<(Option<String>, String)> {
+    let mut r: Vec<(Option<String>, String)> = vec![];
+    match style(lines) {
+        Style::Bracket => {
+            for x in lines {
+                match bracket_label(&x.text) {
+                    Some((l, rest)) => r.push((Some(String::from(l)), String::from(rest))),
+                    None => if let Some(e) = r.last_mut() { join(&mut e.1, &x.text) }
+                }
+            }
+        },
+        Style::Numbered => {
+            let mut expected = 1;
+            for x in lines {
+                match number_label(&x.text) {
+                    Some((n, rest)) if n == expected || r.is_empty() => {
+                        r.push((Some(n.to_string()), String::from(rest)));
+                        expected = n + 1;
+                    },
+                    _ => if let Some(e) = r.last_mut() { join(&mut e.1, &x.text) }
+                }
+            }
+        },
+        Style::Hanging => {
+            let starts = hanging_starts(lines);
+            for (x, &s) in lines.iter().zip(starts.iter()) {
+                if s || r.is_empty() {
+                    r.push((None, String::from(x.text.trim())));
+                }
+                else if let Some(e) = r.last_mut() {
+                    join(&mut e.1, &x.text);
+                }
+            }
+        }
+    }
+    r
+}
+
+fn is_initials(s: &str) -> bool {
+    let w = s.split(|c: char| c.is_whitespace() || c == '-').filter(|x| !x.is_empty()).collect::<Vec<_>>();
+    !w.is_empty() && w.iter().all(|x| {
+        let n = x.chars().count();
+        (x.ends_with('.') && n <= 4) || (n <= 2 && x.chars().all(char::is_uppercase))
+    })
+}
+
+static PARTICLES: [&str; 13] = ["van", "von", "de", "der", "den", "di", "du", "la", "le", "and", "&", "et", "al."];
+
+// capitalised words only, e.g. "Smith", "van der Waals", "A. Author and B. Author"
+fn is_capitalised(s: &str) -> bool {
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    !words.is_empty() && words.len() <= 8 && !s.chars().any(|c| c.is_ascii_digit()) &&
+        words.iter().all(|w| PARTICLES.contains(w) || w.chars().next().map_or(false, |c| c.is_uppercase()))
+}
+
+fn is_author_list(s: &str) -> bool {
+    s.split(',').filter(|x| !x.trim().is_empty()).all(is_capitalised)
+}
+
+// "A. Author", "J.-P. Serre", "and B. C. Author"; unlike a title segment it carries initials
+fn is_name_like(s: &str) -> bool {
+    is_capitalised(s) && s.split_whitespace().any(is_initials)
+}
+
+fn split_authors(s: &str) -> Vec<Author> {
+    let s = s.trim().trim_end_matches(|c: char| c == ',' || c == ':' || c.is_whitespace());
+    let s = s.replace(" et al.", "").replace(" and ", ", ").replace(" & ", ", ").replace(';', ",");
+    let parts = s.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()).collect::<Vec<_>>();
+    let mut r = vec![];
+    let mut i = 0;
+    while i < parts.len() {
+        if i + 1 < parts.len() && is_initials(parts[i+1]) && !is_initials(parts[i]) {
+            r.push(Author{first_name: String::from(parts[i+1]), last_name: String::from(parts[i])});
+            i += 2;
+        }
+        else {
+            r.push(Author::from_name(parts[i]));
+            i += 1;
+        }
+    }
+    r
+}
+
+// "J. Funct. Anal. 254 (2008), 1234–1260" -> "J. Funct. Anal."
+fn venue_name(s: &str) -> Option<String> {
+    let s = s.trim_start_matches(|c: char| c == '.' || c == ',' || c.is_whitespace());
+    let s = if s.starts_with("In ") || s.starts_with("in ") { &s[3..] } else { s };
+    let end = s.find(|c: char| c.is_ascii_digit() || c == '(').unwrap_or(s.len());
+    let v = s[..end].trim().trim_end_matches(|c: char| c == ',' || c == ':' || c == ';' || c.is_whitespace());
+    if v.chars().any(|c| c.is_alphabetic()) {
+        Some(String::from(v))
+    }
+    else {
+        None
+    }
+}
+
+fn sentence(s: &str) -> (&str, &str) {
+    let s = s.trim_start_matches(|c: char| c == '.' || c == ',' || c == ':' || c.is_whitespace());
+    match s.find(". ").or_else(|| s.find("? ")) {
+        Some(i) => (&s[..i+1], &s[i+2..]),
+        None => (s, "")
+    }
+}
+
+fn title(s: &str) -> Option<String> {
+    let t = s.trim().trim_end_matches(|c: char| c == '.' || c == ',' || c.is_whitespace());
+    if t.is_empty() { None } else { Some(String::from(t)) }
+}
+
+// "(2008)" or "(2008a)"
+fn paren_year(s: &str) -> Option<(usize, usize, u32)> {
+    let mut from = 0;
+    while let Some(i) = s[from..].find('(') {
+        let start = from + i;
+        let rest = &s[start+1..];
+        let n = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if n == 4 {
+            let tail = rest[4..].trim_start_matches(|c: char| c.is_ascii_lowercase());
+            if tail.starts_with(')') {
+                let y = rest[..4].parse::<u32>().unwrap();
+                let end = start + 1 + (rest.len() - tail.len()) + 1;
+                return Some((start, end, y))
+            }
+        }
+        from = start + 1;
+    }
+    None
+}
+
+pub fn parse_citation(label: Option<String>, text: &str) -> Citation {
+    let mut c = Citation::default();
+    c.label = label;
+    c.text = String::from(text);
+    c.ids = ident::from_text(text);
+    // identifiers and URLs trail the bibliographic part
+    let mut body = text;
+    for m in ["doi", "http", "arxiv:", "url"].iter() {
+        if let Some(i) = find_ci(body, m) {
+            if i > 20 {
+                body = &body[..i];
+            }
+        }
+    }
+    let py = paren_year(body);
+    c.year = py.map(|x| x.2).or_else(|| find_year(body));
+
+    // author-year: "Author, A. (2008). Title. Venue 12, 34–56."
+    if let Some((start, end, _)) = py {
+        if start > 0 && start * 10 < body.len() * 6 && is_author_list(&body[..start]) {
+            c.authors = split_authors(&body[..start]);
+            let (t, rest) = sentence(&body[end..]);
+            c.title = title(t);
+            c.venue = venue_name(rest);
+            return c
+        }
+    }
+    // quoted title: A. Author, “Title,” Venue ...
+    for &(open, close) in [('“', '”'), ('"', '"')].iter() {
+        if let Some(i) = body.find(open) {
+            if let Some(j) = body[i + open.len_utf8()..].find(close) {
+                let t = &body[i + open.len_utf8() .. i + open.len_utf8() + j];
+                c.authors = split_authors(&body[..i]);
+                c.title = title(t);
+                c.venue = venue_name(&body[i + open.len_utf8() + j + close.len_utf8()..]);
+                return c
+            }
+        }
+    }
+    // Springer: "Author, A., Author, B.: Title. Venue 12, 34–56 (2008)"
+    if let Some(i) = body.find(": ") {
+        if is_author_list(&body[..i]) {
+            c.authors = split_authors(&body[..i]);
+            let (t, rest) = sentence(&body[i+2..]);
+            c.title = title(t);
+            c.venue = venue_name(rest);
+            return c
+        }
+    }
+    // AMS: "A. Author and B. Author, Title of paper, J. Funct. Anal. 254 (2008), 1234–1260."
+    let segs = body.split(", ").collect::<Vec<_>>();
+    let mut n = 0;
+    while n < segs.len() && (is_name_like(segs[n]) || (n > 0 && is_initials(segs[n]))) {
+        n += 1;
+    }
+    c.authors = split_authors(&segs[..n].join(", "));
+    // the title runs up to the segment carrying volume and year
+    if n < segs.len() {
+        let k = (n + 1 .. segs.len()).find(|&i| segs[i].chars().any(|c| c.is_ascii_digit())).unwrap_or(segs.len());
+        c.title = title(&segs[n..k].join(", "));
+        if k < segs.len() {
+            c.venue = venue_name(segs[k]);
+        }
+    }
+    c
+}
+
+pub fn references(pages: &[Vec<TT>]) -> Vec<Citation> {
+    let (p, l) = match find_section(pages) {
+        Some(x) => x,
+        None => return vec![]
+    };
+    let mut lines = vec![];
+    for i in p .. pages.len() {
+        let from = if i == p { l + 1 } else { 0 };
+        for x in &pages[i][from..] {
+            if !x.text.trim().is_empty() {
+                lines.push(x);
+            }
+        }
+    }
+    if lines.is_empty() {
+        return vec![]
+    }
+    segment(&lines).into_iter().map(|(label, text)| parse_citation(label, &text)).collect()
+}
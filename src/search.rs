@@ -0,0 +1,348 @@
+// Inverted index over the page text of the library, kept in a JSON file
+
+use std::collections::HashMap;
+use std::io;
+
+use serde_json::{json, Value};
+
+use crate::item::Item;
+use crate::tags::Notes;
+
+pub struct Doc {
+    pub file: String,
+    pub hash: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub year: Option<u32>,
+    pub ids: Vec<DocId>,
+    pub pages: Vec<String>,
+}
+
+// an Identifier of the item, as names so the index needs no parsing
+#[derive(Debug,Clone)]
+pub struct DocId {
+    pub kind: String,
+    pub value: String,
+    pub source: String,
+    pub primary: bool,
+}
+
+impl DocId {
+    pub fn to_json(&self) -> Value {
+        json!({"kind": self.kind, "value": self.value, "source": self.source, "primary": self.primary})
+    }
+
+    fn from_json(v: &Value) -> DocId {
+        let s = |k: &str| String::from(v[k].as_str().unwrap_or(""));
+        DocId{kind: s("kind"), value: s("value"), source: s("source"), primary: v["primary"].as_bool().unwrap_or(false)}
+    }
+}
+
+pub struct Index {
+    pub docs: Vec<Doc>,
+    // term -> (doc, page, position)
+    terms: HashMap<String, Vec<(usize, usize, usize)>>,
+    // content hash -> user tags, kept when the file moves or leaves the index
+    pub notes: HashMap<String, Notes>,
+    // documents were replaced since the postings were built
+    stale: bool,
+}
+
+// lowercased words with their byte spans
+pub fn words(s: &str) -> Vec<(String, usize, usize)> {
+    let mut r = vec![];
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(b)) => {
+                r.push((s[b..i].to_lowercase(), b, i));
+                start = None;
+            },
+            _ => ()
+        }
+    }
+    if let Some(b) = start {
+        r.push((s[b..].to_lowercase(), b, s.len()));
+    }
+    r
+}
+
+impl Doc {
+    pub fn new(file: &str, hash: &str, item: &Item, pages: Vec<String>) -> Doc {
+        Doc {
+            file: String::from(file),
+            hash: String::from(hash),
+            title: item.title.clone(),
+            authors: item.authors.iter().map(|x| x.last_name.clone()).collect(),
+            year: item.pub_date.map(|x| x.year),
+            ids: item.ids.iter().enumerate().map(|(i, x)| DocId {
+                kind: String::from(x.id.kind()),
+                value: String::from(x.id.value()),
+                source: String::from(x.source.name()),
+                primary: item.primary == Some(i),
+            }).collect(),
+            pages,
+        }
+    }
+}
+
+impl Index {
+    pub fn new() -> Index {
+        Index{docs: vec![], terms: HashMap::new(), notes: HashMap::new(), stale: false}
+    }
+
+    fn post(&mut self, d: usize) {
+        for (p, text) in self.docs[d].pages.iter().enumerate() {
+            for (pos, (w, _, _)) in words(text).into_iter().enumerate() {
+                self.terms.entry(w).or_insert_with(Vec::new).push((d, p, pos));
+            }
+        }
+    }
+
+    // the file is indexed with this content
+    pub fn unchanged(&self, file: &str, hash: &str) -> bool {
+        self.docs.iter().any(|x| x.file == file && x.hash == hash)
+    }
+
+    // replaces an earlier copy of the same file; its postings are rebuilt once, by reindex
+    pub fn add(&mut self, doc: Doc) {
+        match self.docs.iter().position(|x| x.file == doc.file) {
+            Some(d) => {
+                self.docs[d] = doc;
+                self.stale = true;
+            },
+            None => {
+                self.docs.push(doc);
+                let d = self.docs.len() - 1;
+                self.post(d);
+            }
+        }
+    }
+
+    // drops the documents `f` rejects; their notes stay with the content hash
+    pub fn retain<F: Fn(&Doc) -> bool>(&mut self, f: F) {
+        let n = self.docs.len();
+        self.docs.retain(|d| f(d));
+        if self.docs.len() != n {
+            self.stale = true;
+        }
+    }
+
+    pub fn reindex(&mut self) {
+        if !self.stale {
+            return
+        }
+        self.terms.clear();
+        for d in 0 .. self.docs.len() {
+            self.post(d);
+        }
+        self.stale = false;
+    }
+
+    pub fn load(path: &str) -> io::Result<Index> {
+        let v: Value = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let strs = |x: &Value| x.as_array().map_or(vec![], |a| {
+            a.iter().filter_map(|s| s.as_str()).map(String::from).collect::<Vec<_>>()
+        });
+        let mut r = Index::new();
+        for d in v["docs"].as_array().map_or(&[][..], |x| &x[..]) {
+            r.docs.push(Doc {
+                file: String::from(d["file"].as_str().unwrap_or("")),
+                hash: String::from(d["hash"].as_str().unwrap_or("")),
+                title: String::from(d["title"].as_str().unwrap_or("")),
+                authors: strs(&d["authors"]),
+                year: d["year"].as_u64().map(|x| x as u32),
+                ids: d["ids"].as_array().map_or(vec![], |a| a.iter().map(DocId::from_json).collect()),
+                pages: strs(&d["pages"]),
+            });
+        }
+        if let Some(terms) = v["terms"].as_object() {
+            for (t, hits) in terms {
+                let hits = hits.as_array().map_or(vec![], |a| a.iter().filter_map(|h| {
+                    let h = h.as_array()?;
+                    Some((h.get(0)?.as_u64()? as usize, h.get(1)?.as_u64()? as usize, h.get(2)?.as_u64()? as usize))
+                }).collect());
+                r.terms.insert(t.clone(), hits);
+            }
+        }
+        if let Some(notes) = v["notes"].as_object() {
+            for (h, n) in notes {
+                r.notes.insert(h.clone(), Notes::from_json(n));
+            }
+        }
+        Ok(r)
+    }
+
+    pub fn save(&mut self, path: &str) -> io::Result<()> {
+        self.reindex();
+        let docs = self.docs.iter().map(|d| json!({
+            "file": d.file,
+            "hash": d.hash,
+            "title": d.title,
+            "authors": d.authors,
+            "year": d.year,
+            "ids": d.ids.iter().map(DocId::to_json).collect::<Vec<_>>(),
+            "pages": d.pages,
+        })).collect::<Vec<_>>();
+        let terms = self.terms.iter()
+            .map(|(t, v)| (t.clone(), json!(v.iter().map(|&(a, b, c)| vec![a, b, c]).collect::<Vec<_>>())))
+            .collect::<serde_json::Map<String, Value>>();
+        let notes = self.notes.iter()
+            .filter(|(_, n)| !n.is_empty())
+            .map(|(h, n)| (h.clone(), n.to_json()))
+            .collect::<serde_json::Map<String, Value>>();
+        std::fs::write(path, json!({"docs": docs, "terms": terms, "notes": notes}).to_string())
+    }
+
+    pub fn doc_notes(&self, d: &Doc) -> Option<&Notes> {
+        self.notes.get(&d.hash)
+    }
+
+    fn hits(&self, term: &str) -> &[(usize, usize, usize)] {
+        self.terms.get(term).map_or(&[][..], |x| &x[..])
+    }
+
+    // positions of the first word of each occurrence of the phrase; postings are built in
+    // (doc, page, position) order, so the later words are found by binary search
+    fn phrase(&self, ws: &[String]) -> Vec<(usize, usize, usize)> {
+        let first = match ws.first() {
+            Some(x) => self.hits(x),
+            None => return vec![]
+        };
+        first.iter().filter(|&&(d, p, pos)| {
+            ws[1..].iter().enumerate().all(|(k, w)| self.hits(w).binary_search(&(d, p, pos + k + 1)).is_ok())
+        }).cloned().collect()
+    }
+}
+
+#[derive(Debug,Default)]
+pub struct Query {
+    pub phrases: Vec<Vec<String>>,
+    pub author: Vec<String>,
+    pub tags: Vec<String>,
+    pub year: Option<(u32, u32)>,
+}
+
+// `word "a phrase" author:smith year:2008 year:2000-2010 tag:toread`
+pub fn parse_query(q: &str) -> Query {
+    let mut r = Query::default();
+    let mut rest = q;
+    while !rest.trim().is_empty() {
+        rest = rest.trim_start();
+        let tok;
+        if rest.starts_with('"') {
+            let end = rest[1..].find('"').map_or(rest.len(), |x| x + 1);
+            tok = &rest[1..end];
+            rest = if end < rest.len() { &rest[end+1..] } else { "" };
+            let ws = words(tok).into_iter().map(|x| x.0).collect::<Vec<_>>();
+            if !ws.is_empty() {
+                r.phrases.push(ws);
+            }
+            continue
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        tok = &rest[..end];
+        rest = &rest[end..];
+        if tok.starts_with("author:") {
+            r.author.push(tok["author:".len()..].to_lowercase());
+        }
+        else if tok.starts_with("tag:") {
+            r.tags.push(String::from(&tok["tag:".len()..]));
+        }
+        else if tok.starts_with("year:") {
+            let y = &tok["year:".len()..];
+            let mut it = y.splitn(2, '-').map(|x| x.parse::<u32>().ok());
+            let from = it.next().and_then(|x| x);
+            let to = it.next().map_or(from, |x| x);
+            if let (Some(a), Some(b)) = (from, to) {
+                r.year = Some((a, b));
+            }
+        }
+        else {
+            for (w, _, _) in words(tok) {
+                r.phrases.push(vec![w]);
+            }
+        }
+    }
+    r
+}
+
+pub struct Hit {
+    pub doc: usize,
+    pub score: f64,
+    pub pages: Vec<usize>,
+    pub snippet: String,
+}
+
+fn snippet(text: &str, pos: usize, len: usize) -> String {
+    let ws = words(text);
+    if ws.is_empty() {
+        return String::new()
+    }
+    let from = pos.saturating_sub(8);
+    let to = std::cmp::min(pos + len + 8, ws.len()) - 1;
+    let s = text[ws[from].1 .. ws[to].2].split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{}{}{}", if from > 0 { "…" } else { "" }, s, if to + 1 < ws.len() { "…" } else { "" })
+}
+
+pub fn filter(index: &Index, d: &Doc, q: &Query) -> bool {
+    let tags_ok = q.tags.iter().all(|t| index.doc_notes(d).map_or(false, |n| n.has_tag(t)));
+    let authors_ok = q.author.iter().all(|a| d.authors.iter().any(|x| x.to_lowercase().contains(a.as_str())));
+    let year_ok = match (q.year, d.year) {
+        (Some((a, b)), Some(y)) => a <= y && y <= b,
+        (Some(_), None) => false,
+        _ => true
+    };
+    authors_ok && year_ok && tags_ok
+}
+
+// every phrase must occur; tf-idf over the phrase occurrences
+pub fn search(index: &Index, q: &Query) -> Vec<Hit> {
+    let n = index.docs.len() as f64;
+    let mut scores: HashMap<usize, (f64, Vec<(usize, usize, usize)>)> = HashMap::new();
+    let mut first = true;
+    for ph in &q.phrases {
+        let occ = index.phrase(ph);
+        let mut per_doc: HashMap<usize, Vec<(usize, usize, usize)>> = HashMap::new();
+        for x in occ {
+            per_doc.entry(x.0).or_insert_with(Vec::new).push(x);
+        }
+        let idf = (1.0 + n / (1.0 + per_doc.len() as f64)).ln();
+        if first {
+            for (d, v) in per_doc {
+                scores.insert(d, ((1.0 + v.len() as f64).ln() * idf, v));
+            }
+            first = false;
+        }
+        else {
+            scores.retain(|d, _| per_doc.contains_key(d));
+            for (d, s) in scores.iter_mut() {
+                let v = &per_doc[d];
+                s.0 += (1.0 + v.len() as f64).ln() * idf;
+                s.1.extend(v.iter().cloned());
+            }
+        }
+    }
+    // filters alone list every matching document
+    if q.phrases.is_empty() {
+        for d in 0 .. index.docs.len() {
+            scores.insert(d, (0.0, vec![]));
+        }
+    }
+    let mut r = scores.into_iter()
+        .filter(|(d, _)| filter(index, &index.docs[*d], q))
+        .map(|(d, (score, mut occ))| {
+            occ.sort();
+            let mut pages = occ.iter().map(|x| x.1).collect::<Vec<_>>();
+            pages.dedup();
+            let len = q.phrases.first().map_or(0, |x| x.len());
+            let snippet = occ.first().map_or(String::new(), |&(_, p, pos)| snippet(&index.docs[d].pages[p], pos, len));
+            Hit{doc: d, score, pages, snippet}
+        })
+        .collect::<Vec<_>>();
+    r.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| index.docs[a.doc].file.cmp(&index.docs[b.doc].file)));
+    r
+}
@@ -4,6 +4,8 @@ extern crate glib_sys;
 #[macro_use]
 extern crate clap;
 
+mod block_config;
+
 //use std::io::Read;
 //mod item;
 //
@@ -131,7 +133,7 @@ fn b_box(p: &[poppler::ffi::PopplerRectangle]) -> BBox {
     return BBox{left, top, right, bottom}
 }
 
-fn is_complete_order(x: &[TT]) -> bool {
+fn is_complete_order(x: &[TT], cfg: &block_config::Config) -> bool {
     let mut r = vec![];
     for i in 0 .. x.len() {
         for j in 0 .. x.len() {
@@ -142,14 +144,12 @@ fn is_complete_order(x: &[TT]) -> bool {
                 if !is_succ(x, &x[i], &x[j]) || !is_succ(x, &x[j], &x[k]) {
                     continue
                 }
-                let magic1 = 1.0;
-                let magic2 = 0.3;
                 let d1 = x[i].b_box.dist(&x[j].b_box);
                 let d2 = x[j].b_box.dist(&x[k].b_box);
-                if d1 > magic1 * x[i].font_size {
+                if d1 > cfg.max_gap * x[i].font_size {
                     continue 
                 }
-                if (d1 - d2).abs() > magic2 * x[i].font_size  {
+                if (d1 - d2).abs() > cfg.spacing_tolerance * x[i].font_size  {
                     continue
                 }
                 r.push((i,j,k));
@@ -207,7 +207,7 @@ fn is_succ(t: &[TT], x1: &TT, x2: &TT) -> bool {
     return true
 }
 
-fn process_page(doc: &poppler::PopplerDocument, num: usize) -> Result<(),glib::error::Error> {
+fn process_page(doc: &poppler::PopplerDocument, num: usize, cfg: &block_config::Config) -> Result<(),glib::error::Error> {
     let page = doc.get_page(num)?;
     let text = page.get_text();
     let layout = page.get_text_layout()?;
@@ -237,16 +237,16 @@ fn process_page(doc: &poppler::PopplerDocument, num: usize) -> Result<(),glib::e
         char_cnt += 1;
     }
 
-    is_complete_order(&strings);
+    is_complete_order(&strings, cfg);
     //println!("{:?}", strings);
 
     Ok(())
 }
 
-fn springer(fname: &str) -> Result<(),glib::error::Error> {
+fn springer(fname: &str, cfg: &block_config::Config) -> Result<(),glib::error::Error> {
     let doc = poppler::PopplerDocument::new_from_file(fname, "")?;
 
-    process_page(&doc, 0)?;
+    process_page(&doc, 0, cfg)?;
     
     Ok(())
 }
@@ -258,6 +258,16 @@ fn main() {
             .short("i")
             .long("invert")
             .help("Invert selection"))
+        .arg(clap::Arg::with_name("max-gap")
+            .long("max-gap")
+            .takes_value(true)
+            .default_value("1.0")
+            .help("Never join lines further apart than this many font sizes"))
+        .arg(clap::Arg::with_name("spacing-tolerance")
+            .long("spacing-tolerance")
+            .takes_value(true)
+            .default_value("0.3")
+            .help("Allowed difference of successive line gaps, in font sizes"))
         .arg(clap::Arg::with_name("FILE")
             .help("Name of input file")
             .required(true)
@@ -266,5 +276,10 @@ fn main() {
 
     let _invt = matches.is_present("invert");
     let file = matches.value_of("FILE").unwrap();
-    springer(file).unwrap();
+    let cfg = block_config::Config {
+        max_gap: value_t!(matches, "max-gap", f64).unwrap_or_else(|e| e.exit()),
+        spacing_tolerance: value_t!(matches, "spacing-tolerance", f64).unwrap_or_else(|e| e.exit()),
+        ..block_config::Config::default()
+    };
+    springer(file, &cfg).unwrap();
 }
@@ -0,0 +1,282 @@
+// Tables found as runs of rows whose word groups line up in columns, labelled by a nearby
+// "Table N" caption. Rows come from the words of layout::page_words, cells from gaps wider
+// than CELL_GAP font sizes.
+
+use crate::layout::{BBox, Word, TT};
+use crate::normalize::normalize_line;
+use crate::reading_order::gutters;
+
+#[derive(Debug,Clone)]
+pub struct Table {
+    pub page: usize,
+    pub caption: Option<String>,
+    pub b_box: BBox,
+    pub rows: Vec<Vec<String>>,
+}
+
+const CELL_GAP: f64 = 1.0;
+// cells of a table row are short; two columns of body text are not a table
+const WIDE: f64 = 0.3;
+// column edges this close (in font sizes) line up
+const ALIGN: f64 = 0.5;
+
+#[derive(Debug,Clone)]
+struct Segment {
+    text: String,
+    left: f64,
+    right: f64,
+}
+
+#[derive(Debug,Clone)]
+struct Row {
+    segs: Vec<Segment>,
+    top: f64,
+    bottom: f64,
+    size: f64,
+}
+
+fn segment(ws: &[&Word]) -> Segment {
+    Segment {
+        text: ws.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" "),
+        left: ws[0].b_box.left,
+        right: ws.iter().map(|w| w.b_box.right).fold(std::f64::MIN, f64::max),
+    }
+}
+
+// words on one baseline, split into segments at wide gaps
+fn rows(words: &[Word]) -> Vec<Row> {
+    let mut ws = words.iter().collect::<Vec<_>>();
+    ws.sort_by(|a, b| a.baseline.partial_cmp(&b.baseline).unwrap_or(std::cmp::Ordering::Equal));
+    let mut groups: Vec<Vec<&Word>> = vec![];
+    for w in ws {
+        let same = match groups.last() {
+            Some(g) => (w.baseline - g[0].baseline).abs() < 0.5 * g[0].font_size.max(w.font_size),
+            None => false
+        };
+        if same {
+            groups.last_mut().unwrap().push(w);
+        }
+        else {
+            groups.push(vec![w]);
+        }
+    }
+    groups.into_iter().map(|mut g| {
+        g.sort_by(|a, b| a.b_box.left.partial_cmp(&b.b_box.left).unwrap_or(std::cmp::Ordering::Equal));
+        let size = g.iter().map(|w| w.font_size).fold(0.0, f64::max);
+        let mut segs = vec![];
+        let mut from = 0;
+        for i in 1 ..= g.len() {
+            if i == g.len() || g[i].b_box.left - g[i - 1].b_box.right > CELL_GAP * size {
+                segs.push(segment(&g[from .. i]));
+                from = i;
+            }
+        }
+        Row {
+            top: g.iter().map(|w| w.b_box.top).fold(std::f64::MAX, f64::min),
+            bottom: g.iter().map(|w| w.b_box.bottom).fold(std::f64::MIN, f64::max),
+            size,
+            segs,
+        }
+    }).collect()
+}
+
+// rows cut at the column gutters of the page, unless a segment bridges one
+fn split_columns(rows: Vec<Row>, gutters: &[(f64, f64)]) -> Vec<Vec<Row>> {
+    let mut streams: Vec<Vec<Row>> = vec![vec![]; gutters.len() + 2];
+    for r in rows {
+        if r.segs.iter().any(|s| gutters.iter().any(|g| s.left < g.0 && s.right > g.1)) {
+            streams[gutters.len() + 1].push(r);
+            continue
+        }
+        let mut parts: Vec<Row> = vec![];
+        for s in r.segs {
+            let c = gutters.iter().filter(|g| (g.0 + g.1) / 2.0 < s.left).count();
+            while parts.len() <= c {
+                parts.push(Row{segs: vec![], top: r.top, bottom: r.bottom, size: r.size});
+            }
+            parts[c].segs.push(s);
+        }
+        for (c, p) in parts.into_iter().enumerate() {
+            if !p.segs.is_empty() {
+                streams[c].push(p);
+            }
+        }
+    }
+    streams
+}
+
+fn tabular(r: &Row, width: f64) -> bool {
+    r.segs.len() >= 2 && r.segs.iter().filter(|s| s.right - s.left > WIDE * width).count() <= 1
+}
+
+fn aligned(a: &Row, b: &Row) -> bool {
+    let tol = ALIGN * a.size.max(b.size);
+    let close = |x: f64, y: f64| (x - y).abs() <= tol;
+    let n = b.segs.iter().filter(|s| a.segs.iter().any(|t| {
+        close(s.left, t.left) || close(s.right, t.right) || close(s.left + s.right, t.left + t.right)
+    })).count();
+    n >= std::cmp::min(2, b.segs.len())
+}
+
+// runs of aligned tabular rows; single-segment rows inside a run continue a cell
+fn runs(rows: &[Row], width: f64) -> Vec<Vec<usize>> {
+    let mut r = vec![];
+    let mut cur: Vec<usize> = vec![];
+    let mut last: Option<usize> = None;
+    for (i, row) in rows.iter().enumerate() {
+        let near = match cur.last() {
+            Some(&j) => row.top - rows[j].bottom < 1.5 * row.size,
+            None => false
+        };
+        if tabular(row, width) {
+            if near && last.map_or(false, |l| aligned(&rows[l], row)) {
+                cur.push(i);
+            }
+            else {
+                r.push(std::mem::replace(&mut cur, vec![i]));
+            }
+            last = Some(i);
+            continue
+        }
+        let inside = match last {
+            Some(l) => {
+                let left = rows[l].segs[0].left - ALIGN * row.size;
+                let right = rows[l].segs[rows[l].segs.len() - 1].right + ALIGN * row.size;
+                row.segs[0].left >= left && row.segs[0].right <= right
+            },
+            None => false
+        };
+        if near && inside {
+            cur.push(i);
+        }
+        else {
+            r.push(std::mem::replace(&mut cur, vec![]));
+            last = None;
+        }
+    }
+    r.push(cur);
+    r.into_iter().map(|mut x| {
+        while x.last().map_or(false, |&i| !tabular(&rows[i], width)) {
+            x.pop();
+        }
+        x
+    }).filter(|x| !x.is_empty()).collect()
+}
+
+pub fn is_caption(s: &str) -> bool {
+    let mut it = s.trim().split_whitespace();
+    match (it.next(), it.next()) {
+        (Some(t), Some(n)) if t == "Table" || t == "TABLE" => {
+            let n = n.trim_end_matches(|c: char| c == '.' || c == ':');
+            !n.is_empty() && (n.chars().all(|c| c.is_ascii_digit()) || n.chars().all(|c| "IVX".contains(c)))
+        },
+        _ => false
+    }
+}
+
+fn caption(lines: &[TT], b: &BBox, size: f64) -> Option<String> {
+    lines.iter()
+        .filter(|t| is_caption(&t.text) && t.b_box.left < b.right && b.left < t.b_box.right)
+        .map(|t| {
+            let d = if t.b_box.bottom <= b.top { b.top - t.b_box.bottom } else { t.b_box.top - b.bottom };
+            (d, t)
+        })
+        .filter(|&(d, _)| d >= -1.0 && d < 4.0 * size)
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, t)| normalize_line(t.text.trim()))
+}
+
+// the x-extents of the rows with the most cells, overlapping ones merged
+fn columns(rows: &[&Row]) -> Vec<(f64, f64)> {
+    let most = rows.iter().map(|r| r.segs.len()).max().unwrap_or(0);
+    let mut iv = rows.iter().filter(|r| r.segs.len() == most)
+        .flat_map(|r| r.segs.iter().map(|s| (s.left, s.right)))
+        .collect::<Vec<_>>();
+    iv.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let mut r: Vec<(f64, f64)> = vec![];
+    for x in iv {
+        match r.last_mut() {
+            Some(c) if x.0 <= c.1 => c.1 = c.1.max(x.1),
+            _ => r.push(x)
+        }
+    }
+    r
+}
+
+fn column_of(cols: &[(f64, f64)], s: &Segment) -> usize {
+    let c = (s.left + s.right) / 2.0;
+    let dist = |x: &(f64, f64)| if c < x.0 { x.0 - c } else if c > x.1 { c - x.1 } else { 0.0 };
+    (0 .. cols.len()).min_by(|&a, &b| dist(&cols[a]).partial_cmp(&dist(&cols[b])).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(0)
+}
+
+fn push_cell(cell: &mut String, s: &str) {
+    if !cell.is_empty() {
+        cell.push(' ');
+    }
+    cell.push_str(&normalize_line(s));
+}
+
+fn table(page: usize, lines: &[TT], rows: &[&Row], width: f64) -> Option<Table> {
+    let segs = || rows.iter().flat_map(|r| r.segs.iter());
+    let b_box = BBox {
+        top: rows[0].top,
+        left: segs().map(|s| s.left).fold(std::f64::MAX, f64::min),
+        bottom: rows.iter().map(|r| r.bottom).fold(std::f64::MIN, f64::max),
+        right: segs().map(|s| s.right).fold(std::f64::MIN, f64::max),
+    };
+    let caption = caption(lines, &b_box, rows[0].size);
+    let n = rows.iter().filter(|r| tabular(r, width)).count();
+    if n < 3 && !(n == 2 && caption.is_some()) {
+        return None
+    }
+    let cols = columns(rows);
+    let mut cells: Vec<Vec<String>> = vec![];
+    for r in rows {
+        if tabular(r, width) || cells.is_empty() {
+            cells.push(vec![String::new(); cols.len()]);
+        }
+        let row = cells.last_mut().unwrap();
+        for s in &r.segs {
+            push_cell(&mut row[column_of(&cols, s)], &s.text);
+        }
+    }
+    Some(Table{page, caption, b_box, rows: cells})
+}
+
+pub fn page_tables(page: usize, lines: &[TT], words: &[Word]) -> Vec<Table> {
+    if words.is_empty() {
+        return vec![]
+    }
+    let left = words.iter().map(|w| w.b_box.left).fold(std::f64::MAX, f64::min);
+    let right = words.iter().map(|w| w.b_box.right).fold(std::f64::MIN, f64::max);
+    let width = right - left;
+    let mut r = vec![];
+    for mut stream in split_columns(rows(words), &gutters(lines)) {
+        stream.sort_by(|a, b| a.top.partial_cmp(&b.top).unwrap_or(std::cmp::Ordering::Equal));
+        for run in runs(&stream, width) {
+            if let Some(t) = table(page, lines, &run.iter().map(|&i| &stream[i]).collect::<Vec<_>>(), width) {
+                r.push(t);
+            }
+        }
+    }
+    r.sort_by(|a, b| a.b_box.top.partial_cmp(&b.b_box.top).unwrap_or(std::cmp::Ordering::Equal));
+    r
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    }
+    else {
+        String::from(s)
+    }
+}
+
+pub fn to_csv(t: &Table) -> String {
+    let mut s = String::new();
+    for r in &t.rows {
+        s.push_str(&r.iter().map(|x| csv_field(x)).collect::<Vec<_>>().join(","));
+        s.push('\n');
+    }
+    s
+}
@@ -0,0 +1,63 @@
+// User tags, rating and note for a file, keyed by the SHA-256 of its content
+
+use std::io::{self, Read};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug,Default,Clone)]
+pub struct Notes {
+    pub tags: Vec<String>,
+    pub rating: Option<u8>,
+    pub note: Option<String>,
+}
+
+pub fn content_hash(file: &str) -> io::Result<String> {
+    let mut f = std::fs::File::open(file)?;
+    let mut h = Sha256::new();
+    let mut buf = [0u8; 65536];
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break
+        }
+        h.input(&buf[..n]);
+    }
+    Ok(format!("{:x}", h.result()))
+}
+
+impl Notes {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.rating.is_none() && self.note.is_none()
+    }
+
+    // tags differing only in case are the same tag; the first spelling is kept
+    pub fn add_tag(&mut self, t: &str) {
+        if !self.has_tag(t) {
+            self.tags.push(String::from(t));
+            self.tags.sort();
+        }
+    }
+
+    pub fn remove_tag(&mut self, t: &str) {
+        self.tags.retain(|x| !x.eq_ignore_ascii_case(t));
+    }
+
+    pub fn has_tag(&self, t: &str) -> bool {
+        self.tags.iter().any(|x| x.eq_ignore_ascii_case(t))
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({"tags": self.tags, "rating": self.rating, "note": self.note})
+    }
+
+    pub fn from_json(v: &Value) -> Notes {
+        Notes {
+            tags: v["tags"].as_array().map_or(vec![], |a| {
+                a.iter().filter_map(|x| x.as_str()).map(String::from).collect()
+            }),
+            rating: v["rating"].as_u64().map(|x| x as u8),
+            note: v["note"].as_str().map(String::from),
+        }
+    }
+}
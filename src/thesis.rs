@@ -0,0 +1,215 @@
+use crate::item::Thesis;
+
+static MARKERS: [&str; 11] = [
+    "dissertation submitted",
+    "thesis submitted",
+    "doctor of philosophy",
+    "in partial fulfillment of the requirements",
+    "in partial fulfilment of the requirements",
+    "thèse de doctorat",
+    "thèse présentée",
+    "pour obtenir le grade de docteur",
+    "dissertation zur erlangung",
+    "zur erlangung des",
+    "master's thesis",
+];
+
+// matched as whole words, "master" is not "mastermind"
+static DEGREES: [(&str, &str); 14] = [
+    ("doctor of philosophy", "Doctor of Philosophy"),
+    ("ph.d", "Doctor of Philosophy"),
+    ("doctor of science", "Doctor of Science"),
+    ("master of science", "Master of Science"),
+    ("master of arts", "Master of Arts"),
+    ("master's thesis", "Master"),
+    ("thèse de doctorat", "Doctorat"),
+    ("grade de docteur", "Doctorat"),
+    ("doctor rerum naturalium", "Dr. rer. nat."),
+    ("dr. rer. nat.", "Dr. rer. nat."),
+    ("doktorgrades", "Doktor"),
+    ("doktors", "Doktor"),
+    ("doktor", "Doktor"),
+    ("master", "Master"),
+];
+
+static INSTITUTIONS: [&str; 10] = [
+    "university", "université", "universität", "universitat", "universidad", "università",
+    "institute of technology", "école", "institut", "college",
+];
+
+static DEPARTMENTS: [&str; 10] = [
+    "department of", "faculty of", "school of", "graduate school", "école doctorale",
+    "département", "faculté", "fakultät", "fachbereich", "institut für",
+];
+
+// whole words too, the plurals before the singulars: "Supervisors:" and "Supervisor:"
+static SUPERVISORS: [&str; 15] = [
+    "under the supervision of", "supervised by", "supervisors", "supervisor", "advisors", "advisor",
+    "advisers", "adviser", "directeur de thèse", "directrice de thèse", "sous la direction de",
+    "betreut von", "betreuer", "referenten", "referent",
+];
+
+// `pat` must be lowercase
+fn starts_with_ci(s: &str, pat: &str) -> bool {
+    let mut it = s.chars().flat_map(|c| c.to_lowercase());
+    for p in pat.chars() {
+        match it.next() {
+            Some(c) if c == p => (),
+            _ => return false
+        }
+    }
+    true
+}
+
+pub fn find_ci(s: &str, pat: &str) -> Option<usize> {
+    for (i, _) in s.char_indices() {
+        if starts_with_ci(&s[i..], pat) {
+            return Some(i)
+        }
+    }
+    None
+}
+
+fn find_any(s: &str, pats: &[&str]) -> Option<(usize, usize)> {
+    for (n, p) in pats.iter().enumerate() {
+        if let Some(i) = find_ci(s, p) {
+            return Some((i, n))
+        }
+    }
+    None
+}
+
+// like find_ci, but not inside a longer word
+fn find_word_ci(s: &str, pat: &str) -> Option<usize> {
+    let n = pat.chars().count();
+    for (i, _) in s.char_indices() {
+        if !starts_with_ci(&s[i..], pat) {
+            continue
+        }
+        let before = s[..i].chars().last().map_or(false, |c| c.is_alphanumeric());
+        let after = s[i..].chars().nth(n).map_or(false, |c| c.is_alphanumeric());
+        if !before && !after {
+            return Some(i)
+        }
+    }
+    None
+}
+
+fn find_any_word(s: &str, pats: &[&str]) -> Option<(usize, usize)> {
+    for (n, p) in pats.iter().enumerate() {
+        if let Some(i) = find_word_ci(s, p) {
+            return Some((i, n))
+        }
+    }
+    None
+}
+
+fn clean(s: &str) -> Option<String> {
+    let r = s.trim_matches(|c: char| c.is_whitespace() || c == ',' || c == ':' || c == ';' || c == '.');
+    if r.is_empty() {
+        None
+    }
+    else {
+        Some(String::from(r))
+    }
+}
+
+// Extend a keyword match backwards over capitalised words,
+// e.g. "Harvard University" or "Technische Universität München".
+fn institution_start(seg: &str, pos: usize) -> usize {
+    let mut start = match seg[..pos].rfind(' ') {
+        Some(i) => i + 1,
+        None => 0
+    };
+    while start > 0 {
+        let head = seg[..start].trim_end();
+        let w = match head.rfind(' ') {
+            Some(i) => i + 1,
+            None => 0
+        };
+        match head[w..].chars().next() {
+            Some(c) if c.is_uppercase() => start = w,
+            _ => break
+        }
+    }
+    start
+}
+
+pub fn find_year(s: &str) -> Option<u32> {
+    let mut r = None;
+    for w in s.split(|c: char| !c.is_ascii_digit()) {
+        if w.len() != 4 {
+            continue
+        }
+        let y = w.parse::<u32>().unwrap();
+        if y >= 1900 && y <= 2100 {
+            r = Some(y);
+        }
+    }
+    r
+}
+
+static SUBMISSION: [&str; 6] = [
+    "submitted", "submission", "defended", "soutenue", "eingereicht", "vorgelegt",
+];
+
+// the year on the line naming the degree or the submission, or close below it after the
+// institution, or right above it; the last year on the page may be a copyright or a reprint
+fn degree_year(lines: &[&str]) -> Option<u32> {
+    let degrees = DEGREES.iter().map(|&(p, _)| p).collect::<Vec<_>>();
+    for (n, line) in lines.iter().enumerate() {
+        if find_any_word(line, &degrees).is_none() && find_any(line, &MARKERS).is_none() &&
+            find_any_word(line, &SUBMISSION).is_none() {
+            continue
+        }
+        let near = [Some(n), Some(n + 1), Some(n + 2), Some(n + 3), n.checked_sub(1)];
+        if let Some(y) = near.iter().filter_map(|&k| lines.get(k?)).filter_map(|x| find_year(x)).next() {
+            return Some(y)
+        }
+    }
+    None
+}
+
+pub fn is_thesis(text: &str) -> bool {
+    find_any(text, &MARKERS).is_some()
+}
+
+pub fn thesis(text: &str) -> Option<Thesis> {
+    if !is_thesis(text) {
+        return None
+    }
+    let mut r = Thesis::default();
+    r.degree = find_any_word(text, &DEGREES.iter().map(|&(p, _)| p).collect::<Vec<_>>())
+        .map(|(_, n)| String::from(DEGREES[n].1));
+    let lines = text.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).collect::<Vec<_>>();
+    r.year = degree_year(&lines).or_else(|| find_year(text));
+    for (n, line) in lines.iter().enumerate() {
+        for seg in line.split(',') {
+            if r.department.is_none() {
+                if let Some((i, _)) = find_any(seg, &DEPARTMENTS) {
+                    r.department = clean(&seg[i..]);
+                    continue
+                }
+            }
+            if r.institution.is_none() {
+                if let Some((i, _)) = find_any(seg, &INSTITUTIONS) {
+                    r.institution = clean(&seg[institution_start(seg, i)..]);
+                }
+            }
+        }
+        if r.supervisor.is_none() {
+            if let Some((i, k)) = find_any_word(line, &SUPERVISORS) {
+                let n_chars = SUPERVISORS[k].chars().count();
+                let rest = match line[i..].char_indices().nth(n_chars) {
+                    Some((j, _)) => &line[i + j..],
+                    None => ""
+                };
+                r.supervisor = match clean(rest) {
+                    Some(x) => Some(x),
+                    None => lines.get(n + 1).and_then(|x| clean(x))
+                };
+            }
+        }
+    }
+    Some(r)
+}
@@ -0,0 +1,75 @@
+// PDFs dropped into a directory, reported once writes to them have settled
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use inotify::{Inotify, WatchMask};
+
+pub struct Inbox {
+    inotify: Inotify,
+    dir: PathBuf,
+    settle: Duration,
+    // last event and the size seen at the last poll
+    pending: HashMap<PathBuf, (Instant, u64)>,
+}
+
+fn is_pdf(p: &Path) -> bool {
+    p.extension().and_then(|x| x.to_str()).map_or(false, |x| x.eq_ignore_ascii_case("pdf"))
+}
+
+impl Inbox {
+    pub fn new(dir: &str, settle: Duration) -> io::Result<Inbox> {
+        let mut inotify = Inotify::init()?;
+        inotify.add_watch(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::MODIFY | WatchMask::CREATE)?;
+        Ok(Inbox{inotify, dir: PathBuf::from(dir), settle, pending: HashMap::new()})
+    }
+
+    fn poll(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; 4096];
+        let now = Instant::now();
+        for event in self.inotify.read_events(&mut buffer)? {
+            if let Some(name) = event.name {
+                let path = self.dir.join(name);
+                if is_pdf(&path) {
+                    let size = self.pending.get(&path).map_or(0, |x| x.1);
+                    self.pending.insert(path, (now, size));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // blocks until some files have had no events for the settle time and kept their size
+    pub fn next(&mut self) -> io::Result<Vec<PathBuf>> {
+        loop {
+            self.poll()?;
+            let mut ready = vec![];
+            for (path, v) in self.pending.iter_mut() {
+                let size = match std::fs::metadata(path) {
+                    Ok(m) => m.len(),
+                    Err(_) => {
+                        // moved away or deleted before it settled
+                        ready.push((path.clone(), false));
+                        continue
+                    }
+                };
+                if size != v.1 {
+                    *v = (Instant::now(), size);
+                }
+                else if v.0.elapsed() >= self.settle && size > 0 {
+                    ready.push((path.clone(), true));
+                }
+            }
+            for &(ref p, _) in &ready {
+                self.pending.remove(p);
+            }
+            let ready = ready.into_iter().filter(|x| x.1).map(|x| x.0).collect::<Vec<_>>();
+            if !ready.is_empty() {
+                return Ok(ready)
+            }
+            std::thread::sleep(Duration::from_millis(250));
+        }
+    }
+}
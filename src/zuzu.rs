@@ -3,6 +3,11 @@ extern crate glib;
 extern crate glib_sys;
 #[macro_use]
 extern crate clap;
+extern crate unicode_normalization;
+
+mod normalize;
+mod equations;
+mod block_config;
 
 //use std::io::Read;
 //mod item;
@@ -455,7 +460,7 @@ fn split_by_font<'a>(x: &[TT], c: &'a[usize]) -> Vec<&'a[usize]> {
     r
 }
 
-fn split_by_distance<'a>(x: &[TT], c: &'a[usize]) -> Vec<&'a[usize]> {
+fn split_by_distance<'a>(x: &[TT], c: &'a[usize], cfg: &block_config::Config, eq: &[bool]) -> Vec<&'a[usize]> {
     if c.len() < 2 {
         return vec![c]
     }
@@ -464,10 +469,12 @@ fn split_by_distance<'a>(x: &[TT], c: &'a[usize]) -> Vec<&'a[usize]> {
         dv.push(x[c[i-1]].b_box.dist(&x[c[i]].b_box));
     }
     let mut from = 0;
-    let magic_num1 = 0.4 * x[c[0]].b_box.width();
+    let max_gap = cfg.gap_factor * x[c[0]].b_box.width();
     let mut r = vec![];
     for i in 1 .. c.len() {
-        if dv[i-1] > magic_num1 {
+        // display equations are never fused with the prose around them
+        let (e1, e2) = (eq[c[i-1]], eq[c[i]]);
+        if dv[i-1] > max_gap || e1 != e2 || (e1 && equations::equation_number(&x[c[i-1]].text).is_some()) {
             r.push(&c[from .. i]);
             from = i;
         }
@@ -482,18 +489,25 @@ fn merge_tt(t: &[TT], c: &[usize]) -> TT {
    TT{text: s, b_box: b, font_size: t[c[0]].font_size, font_name: String::from(t[c[0]].font_name.as_str())}
 }
 
-fn zuzu1(t1: &[TT]) -> bool {
+fn zuzu1(t1: &[TT], cfg: &block_config::Config, norm: &normalize::Normalizer) -> bool {
     let p1 = Poset::create_poset(t1);
     assert!(p1.elt.len() == t1.len());
+    // only the dominant font of a line is known here
+    let left = t1.iter().map(|t| t.b_box.left).fold(std::f64::MAX, f64::min);
+    let right = t1.iter().map(|t| t.b_box.right).fold(std::f64::MIN, f64::max);
+    let eq = t1.iter().map(|t| {
+        let math = if equations::is_math_font(&t.font_name) { 1.0 } else { 0.0 };
+        equations::is_display(&t.text, math, t.b_box.left, t.b_box.right, (left, right), t.font_size)
+    }).collect::<Vec<_>>();
     let mut t2 = vec![];
     for c in p1.chain_iter() {
         for i in &split_by_font(t1, &c) {
-            for j in &split_by_distance(t1, i) {
+            for j in &split_by_distance(t1, i, cfg, &eq) {
                 t2.push(merge_tt(t1, j));
             }
         }
     }
-    for i in &t2 {println!("===\n{}", i.text);}
+    for i in &t2 {println!("===\n{}", norm.text(&i.text));}
     let p2 = Poset::create_poset(&t2);
     println!(">>> {}", p2.min.iter().count());
     true
@@ -628,7 +642,7 @@ fn is_pred(t: &[TT], x1: &TT, x2: &TT) -> bool {
     is_succ(t, x2, x1)
 }
 
-fn process_page(doc: &poppler::PopplerDocument, num: usize) -> Result<(),glib::error::Error> {
+fn process_page(doc: &poppler::PopplerDocument, num: usize, cfg: &block_config::Config, norm: &normalize::Normalizer) -> Result<(),glib::error::Error> {
     let page = doc.get_page(num)?;
     let text = page.get_text();
     let layout = page.get_text_layout()?;
@@ -658,16 +672,19 @@ fn process_page(doc: &poppler::PopplerDocument, num: usize) -> Result<(),glib::e
         char_cnt += 1;
     }
 
-    zuzu1(&strings);
+    zuzu1(&strings, cfg, norm);
     //is_complete_order(&strings);
     //println!("{:?}", strings);
 
     Ok(())
 }
 
-fn springer(fname: &str) -> Result<(),glib::error::Error> {
+fn springer(fname: &str, cfg: &block_config::Config, raw: bool) -> Result<(),glib::error::Error> {
     let doc = poppler::PopplerDocument::new_from_file(fname, "")?;
-    process_page(&doc, 0)?;
+    let mut norm = normalize::Normalizer::new();
+    norm.raw = raw;
+    norm.learn(&doc.get_page(0)?.get_text());
+    process_page(&doc, 0, cfg, &norm)?;
     Ok(())
 }
 
@@ -678,6 +695,14 @@ fn main() {
             .short("i")
             .long("invert")
             .help("Invert selection"))
+        .arg(clap::Arg::with_name("gap-factor")
+            .long("gap-factor")
+            .takes_value(true)
+            .default_value("0.4")
+            .help("Split blocks on gaps above this share of the line height"))
+        .arg(clap::Arg::with_name("raw")
+            .long("raw")
+            .help("Print the text as extracted, without normalisation"))
         .arg(clap::Arg::with_name("FILE")
             .help("Name of input file")
             .required(true)
@@ -686,7 +711,11 @@ fn main() {
 
     let _invt = matches.is_present("invert");
     let file = matches.value_of("FILE").unwrap();
-    match springer(file) {
+    let cfg = block_config::Config {
+        gap_factor: value_t!(matches, "gap-factor", f64).unwrap_or_else(|e| e.exit()),
+        ..block_config::Config::default()
+    };
+    match springer(file, &cfg, matches.is_present("raw")) {
         Ok(()) => (),
         Err(e) => {
             println!("ERROR: {}", e);